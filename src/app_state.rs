@@ -1,5 +1,11 @@
-use crate::prelude::*;
-use std::path::PathBuf;
+use crate::{
+    cache::Cache, config::Config, markdown::highlight::Highlighter, prelude::*, search::Search,
+    Metadata,
+};
+use std::{path::PathBuf, sync::Arc};
+use time::{Date, OffsetDateTime};
+use time_tz::{timezones, OffsetDateTimeExt, Tz};
+use tokio::sync::broadcast;
 use tracing::trace;
 
 #[derive(Debug, Clone)]
@@ -7,6 +13,13 @@ pub struct AppState {
     pub root: PathBuf,
     pub md_options: Options,
     pub port: u16,
+    pub config: Arc<Config>,
+    pub highlighter: Arc<Highlighter>,
+    pub cache: Arc<Cache>,
+    pub search: Arc<Search>,
+    pub reload: Option<broadcast::Sender<()>>,
+    pub drafts: bool,
+    pub time_zone: &'static Tz,
 }
 
 impl AppState {
@@ -14,6 +27,31 @@ impl AppState {
         trace!("Building AppState");
         AppStateBuilder::default()
     }
+
+    // Whether a page may be shown, given `--drafts`. Rendered pages are dropped from the cache when
+    // a scheduled page is published or expires
+    pub(crate) fn is_visible(&self, metadata: &Metadata) -> bool {
+        let now = OffsetDateTime::now_utc();
+        if let Some(next) = metadata.next_change(now) {
+            self.cache.expire_at(next.into());
+        }
+        self.drafts || metadata.is_published(now)
+    }
+
+    // The date in the site's time zone, unless the reader asked for another
+    pub fn today(&self, tz: Option<&'static Tz>) -> Date {
+        OffsetDateTime::now_utc()
+            .to_timezone(tz.unwrap_or(self.time_zone))
+            .date()
+    }
+
+    // Without a configured base URL, links point at this server
+    pub fn base_url(&self) -> String {
+        match &self.config.site.base_url {
+            Some(url) => url.trim_end_matches('/').to_string(),
+            None => format!("http://localhost:{}", self.port),
+        }
+    }
 }
 
 #[derive(Default)]
@@ -21,6 +59,9 @@ pub struct AppStateBuilder<R, P> {
     root: R,
     md_options: Option<Options>,
     port: P,
+    config: Option<Config>,
+    watch: bool,
+    drafts: bool,
 }
 
 impl AppStateBuilder<NoRoot, NoPort> {
@@ -36,17 +77,30 @@ impl<P> AppStateBuilder<NoRoot, P> {
             root: Root(root.into()),
             md_options: self.md_options,
             port: self.port,
+            config: self.config,
+            watch: self.watch,
+            drafts: self.drafts,
         }
     }
 }
 
 impl AppStateBuilder<Root, Port> {
     pub fn build(self) -> AppState {
+        let config = self.config.unwrap_or_default();
+        let highlighter = Highlighter::new(&self.root.0, config.site.highlight_theme.as_deref());
+        let time_zone = config.site.time_zone.unwrap_or(timezones::db::UTC);
         trace!("Finished building AppState");
         AppState {
             root: self.root.0,
             md_options: self.md_options.unwrap_or(Options::empty()),
             port: self.port.0,
+            config: Arc::new(config),
+            highlighter: Arc::new(highlighter),
+            cache: Arc::default(),
+            search: Arc::default(),
+            reload: self.watch.then(|| broadcast::channel(16).0),
+            drafts: self.drafts,
+            time_zone,
         }
     }
 }
@@ -58,6 +112,9 @@ impl<R> AppStateBuilder<R, NoPort> {
             root: self.root,
             md_options: self.md_options,
             port: Port(port),
+            config: self.config,
+            watch: self.watch,
+            drafts: self.drafts,
         }
     }
 }
@@ -68,6 +125,24 @@ impl<R, P> AppStateBuilder<R, P> {
         self.md_options = Some(md_options);
         self
     }
+
+    pub fn config(mut self, config: Config) -> Self {
+        trace!("Setting site config");
+        self.config = Some(config);
+        self
+    }
+
+    pub fn watch(mut self, watch: bool) -> Self {
+        trace!("Setting live reload");
+        self.watch = watch;
+        self
+    }
+
+    pub fn drafts(mut self, drafts: bool) -> Self {
+        trace!("Setting draft visibility");
+        self.drafts = drafts;
+        self
+    }
 }
 
 // TypeState
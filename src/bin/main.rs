@@ -1,7 +1,8 @@
 #![recursion_limit = "512"]
 
-use clap::Parser;
-use webr::{prelude::*, start};
+use clap::{error::ErrorKind, CommandFactory, Parser};
+use std::path::PathBuf;
+use webr::{build::build, prelude::*, start, Command};
 
 #[tokio::main]
 async fn main() -> R<()> {
@@ -11,16 +12,36 @@ async fn main() -> R<()> {
         .init();
 
     let args = Args::parse();
+    // Clap can't require global arguments, which can follow the subcommand
+    let Some(content) = args.content else {
+        Args::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "the following required arguments were not provided:\n  --content <CONTENT>",
+            )
+            .exit();
+    };
 
     let mut md_opts = Options::all();
     md_opts.remove(Options::ENABLE_SMART_PUNCTUATION);
 
+    let config = Config::load(
+        args.config
+            .unwrap_or_else(|| PathBuf::from(&content).join("webr.toml")),
+    )?;
+
     let state = AppState::builder()
-        .root(args.content)
+        .root(content)
         .port(args.port)
         .md_options(md_opts)
+        .config(config)
+        .watch(matches!(args.command, Some(Command::Serve { watch: true })))
+        .drafts(args.drafts)
         .build();
 
-    start(state).await?;
+    match args.command {
+        Some(Command::Build { out }) => build(state, out).await?,
+        Some(Command::Serve { .. }) | None => start(state).await?,
+    }
     Ok(())
 }
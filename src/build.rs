@@ -0,0 +1,294 @@
+//! Static site export
+
+use crate::{
+    cache::Page,
+    feed::{self, FeedKind},
+    lectionary,
+    markdown::{self, INDEX_FILES},
+    prelude::*,
+    render_error_page, sitemap, tags,
+    utils::{self, order::url_path},
+};
+use axum::{extract::State, http::StatusCode};
+use std::path::{Path, PathBuf};
+use tokio::{fs, task::spawn_blocking};
+use tracing::{debug, info, trace, warn};
+
+// Every page is written as `<url>/index.html`, so a static host serves `/foo` and `/foo/` the same
+// way `normalize_path` does
+pub async fn build(state: AppState, out: impl Into<PathBuf>) -> R<()> {
+    let out = out.into();
+    info!(
+        r#"Building site from "{}" into "{}""#,
+        state.root.display(),
+        out.display()
+    );
+
+    if state.config.site.base_url.is_none() {
+        warn!(
+            "No base_url is set in the site config, so feeds, the sitemap and robots.txt will link to {}",
+            state.base_url()
+        );
+    }
+
+    let root = state.root.clone();
+    let entries = spawn_blocking(move || utils::walk(root)).await??;
+
+    write_page(
+        &out,
+        Path::new(""),
+        render_dir(&state, PathBuf::new()).await?.html,
+    )
+    .await?;
+    for rel_path in entries {
+        let fs_path = state.root.join(&rel_path);
+        let is_md = rel_path.extension().is_some_and(|ext| ext == "md");
+
+        if fs_path.is_dir() {
+            write_page(
+                &out,
+                &url_path(&rel_path),
+                render_dir(&state, rel_path.clone()).await?.html,
+            )
+            .await?;
+            write_feeds(&state, &out, &rel_path).await?;
+        } else if is_md {
+            let page_path = url_path(&rel_path);
+            if markdown::is_error_page(&rel_path) {
+                trace!(r#"Skipping error page "{}""#, rel_path.display());
+            } else if rel_path
+                .file_name()
+                .is_some_and(|name| INDEX_FILES.iter().any(|f| name == *f))
+            {
+                trace!(
+                    r#"Index "{}" is built with its directory"#,
+                    rel_path.display()
+                );
+            } else if state.root.join(rel_path.with_extension("")).is_dir() {
+                warn!(
+                    r#""{}" is shadowed by a directory of the same name"#,
+                    rel_path.display()
+                );
+            } else if !markdown::is_page_visible(&state, &fs_path) {
+                debug!(r#"Skipping unpublished page "{}""#, rel_path.display());
+            } else {
+                let state = state.clone();
+                let page =
+                    spawn_blocking(move || markdown::render_markdown(State(state), rel_path))
+                        .await??;
+                write_page(&out, &page_path, page.html).await?;
+            }
+        } else if rel_path.extension().is_some() {
+            copy_file(&fs_path, &out.join(&rel_path)).await?;
+        } else {
+            trace!(r#"Skipping "{}""#, rel_path.display());
+        }
+    }
+
+    let root = state.root.clone();
+    for rel_path in spawn_blocking(move || utils::hidden_assets(root)).await?? {
+        copy_file(&state.root.join(&rel_path), &out.join(&rel_path)).await?;
+    }
+
+    // Static hosts commonly serve `404.html` for missing pages
+    let st = state.clone();
+    let not_found = spawn_blocking(move || {
+        render_error_page(
+            &st,
+            StatusCode::NOT_FOUND,
+            &Error::NotFound(PathBuf::from("This page")),
+        )
+    })
+    .await??;
+    fs::write(out.join("404.html"), not_found).await?;
+
+    let stylesheet = out.join(markdown::highlight::STYLESHEET_PATH.trim_start_matches('/'));
+    if let Some(parent) = stylesheet.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    fs::write(stylesheet, state.highlighter.stylesheet()).await?;
+
+    write_feeds(&state, &out, Path::new("")).await?;
+
+    let st = state.clone();
+    let sitemap = spawn_blocking(move || sitemap::sitemap_xml(&st)).await??;
+    fs::write(out.join("sitemap.xml"), sitemap).await?;
+    if !state.root.join("robots.txt").is_file() {
+        fs::write(out.join("robots.txt"), sitemap::robots_txt(&state)).await?;
+    }
+
+    let st = state.clone();
+    let tag_pages = spawn_blocking(move || -> R<Vec<(PathBuf, String)>> {
+        let index = tags::index(&st)?;
+        let mut pages = vec![(PathBuf::from("tags"), tags::render_tags(&st, &index)?)];
+        for slug in index.keys() {
+            let html = tags::render_tag(&st, &index, slug)?;
+            pages.push((Path::new("tags").join(slug), html));
+        }
+        Ok(pages)
+    })
+    .await??;
+    for (page_path, html) in tag_pages {
+        write_page(&out, &page_path, html).await?;
+    }
+
+    if state.root.join("lectionary.md").is_file() {
+        let today = state.today(None);
+        let days = lectionary::calendar_days(today)?;
+        let mut pages = vec![(PathBuf::from("lectionary"), lectionary::Span::Today)];
+        let mut day = Some(*days.start());
+        while let Some(date) = day.filter(|d| days.contains(d)) {
+            pages.push((
+                Path::new("lectionary").join(date.to_string()),
+                lectionary::Span::Day(date),
+            ));
+            day = date.next_day();
+        }
+        for (page_path, span) in pages {
+            let st = state.clone();
+            let days = days.clone();
+            let res = spawn_blocking(move || {
+                lectionary::lectionary_wrapped(State(st), span, today, days)
+            })
+            .await??;
+            let body = axum::body::to_bytes(res.into_body(), usize::MAX).await?;
+            write_page(&out, &page_path, body).await?;
+        }
+
+        let st = state.clone();
+        let ics =
+            spawn_blocking(move || lectionary::ics(&st, lectionary::Span::Today, today)).await??;
+        fs::write(out.join("lectionary.ics"), ics).await?;
+    } else {
+        debug!("No lectionary.md found, skipping lectionary");
+    }
+
+    info!("Finished building site");
+    Ok(())
+}
+
+// Static hosts ignore query strings, so listings are never split into pages
+async fn render_dir(state: &AppState, rel_path: PathBuf) -> R<Page> {
+    let state = state.clone();
+    Ok(spawn_blocking(move || markdown::render_dir(State(state), rel_path, None)).await??)
+}
+
+// Every directory has feeds, as when serving
+async fn write_feeds(state: &AppState, out: &Path, rel_dir: &Path) -> R<()> {
+    let dest = out.join(url_path(rel_dir));
+    fs::create_dir_all(&dest).await?;
+    for kind in [FeedKind::Rss, FeedKind::Atom] {
+        let st = state.clone();
+        let dir = rel_dir.to_path_buf();
+        let res = spawn_blocking(move || feed::feed(&st, dir, kind)).await??;
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await?;
+        fs::write(dest.join(kind.file_name()), body).await?;
+    }
+    Ok(())
+}
+
+async fn write_page(out: &Path, page_path: &Path, body: impl AsRef<[u8]>) -> R<()> {
+    let dest = out.join(page_path).join("index.html");
+    trace!(r#"Writing "{}""#, dest.display());
+    fs::create_dir_all(dest.parent().unwrap_or(out)).await?;
+    fs::write(dest, body).await?;
+    Ok(())
+}
+
+async fn copy_file(from: &Path, to: &Path) -> R<()> {
+    trace!(r#"Copying "{}""#, from.display());
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    fs::copy(from, to).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::read_to_string;
+
+    #[tokio::test]
+    async fn builds_output_tree() {
+        let (_root, state) = utils::test_site(&[
+            ("index.md", "# Home"),
+            ("about.md", "# About"),
+            ("404.md", "# {{ status }} {{ reason }}"),
+            ("style.css", "body {}"),
+            ("01-guide/02-setup.md", "# Setup"),
+            ("01-guide/cat.png", "png"),
+            ("drafts/wip.md", "```toml\ndraft = true\n```\n# WIP"),
+            (".git/config", "[core]"),
+            (".git/hooks/pre-commit.sample", "#!/bin/sh"),
+            ("notes/.secret.md", "# Secret"),
+            ("lectionary.md", "# Lectionary"),
+            ("01-guide/.cat", "A cat"),
+            ("01-guide/.order", "02-setup.md"),
+            (".fonts/serif.woff2", "woff2"),
+            (".syntaxes/toml.sublime-syntax", "%YAML 1.2"),
+        ]);
+        let out = tempfile::tempdir().unwrap();
+        build(state, out.path()).await.unwrap();
+
+        let mut files = utils::walk(out.path())
+            .unwrap()
+            .into_iter()
+            .filter(|p| out.path().join(p).is_file())
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        files.sort();
+        for expected in [
+            "404.html",
+            "about/index.html",
+            "guide/index.html",
+            "guide/setup/index.html",
+            "01-guide/cat.png",
+            "drafts/index.html",
+            "index.html",
+            "notes/index.html",
+            "style.css",
+            "sitemap.xml",
+            "robots.txt",
+            "feed.xml",
+            "atom.xml",
+            "guide/feed.xml",
+            "guide/atom.xml",
+        ] {
+            assert!(
+                files.iter().any(|f| f == expected),
+                "{expected} in {files:?}"
+            );
+        }
+        for unexpected in [
+            "404/index.html",
+            "drafts/wip/index.html",
+            "index/index.html",
+        ] {
+            assert!(!files.iter().any(|f| f == unexpected), "{unexpected}");
+        }
+        assert!(out.path().join(".fonts/serif.woff2").is_file());
+        for private in [
+            ".git",
+            ".syntaxes",
+            "notes/.secret.md",
+            "01-guide/.cat",
+            "01-guide/.order",
+        ] {
+            assert!(!out.path().join(private).exists(), "{private}");
+        }
+        assert!(out.path().join(".webr/highlight.css").is_file());
+
+        // The index and a page for each day in the calendar
+        assert_eq!(
+            std::fs::read_dir(out.path().join("lectionary"))
+                .unwrap()
+                .count(),
+            1 + 365
+        );
+        assert!(out.path().join("lectionary.ics").is_file());
+
+        let not_found = read_to_string(out.path().join("404.html")).unwrap();
+        assert!(not_found.contains("404 Not Found"));
+    }
+}
@@ -0,0 +1,140 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        RwLock,
+    },
+    time::SystemTime,
+};
+use tracing::{debug, trace};
+
+// Rendered pages, along with every file they were rendered from
+#[derive(Debug, Default)]
+pub struct Cache {
+    entries: RwLock<HashMap<String, Entry>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    // When the next scheduled page is published or expires
+    expires: RwLock<Option<SystemTime>>,
+}
+
+#[derive(Debug)]
+pub struct Page {
+    pub html: String,
+    // `Link` header for paginated pages
+    pub link: Option<String>,
+    pub deps: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Rendered {
+    pub html: String,
+    pub link: Option<String>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    rendered: Rendered,
+    deps: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl Entry {
+    // A dependency which did not exist when rendering must still not exist
+    fn is_fresh(&self) -> bool {
+        self.deps
+            .iter()
+            .all(|(path, modified)| &last_modified(path) == modified)
+    }
+}
+
+impl Cache {
+    pub fn get_or_render<E>(
+        &self,
+        key: &str,
+        render: impl FnOnce() -> Result<Page, E>,
+    ) -> Result<Rendered, E> {
+        self.expire();
+        let cached = self
+            .entries
+            .read()
+            .expect("render cache lock poisoned")
+            .get(key)
+            .filter(|entry| entry.is_fresh())
+            .map(|entry| entry.rendered.clone());
+
+        if let Some(rendered) = cached {
+            let hits = self.hits.fetch_add(1, Ordering::Relaxed) + 1;
+            debug!(
+                hits,
+                misses = self.misses.load(Ordering::Relaxed),
+                r#"Render cache hit for "{key}""#
+            );
+            return Ok(rendered);
+        }
+
+        let misses = self.misses.fetch_add(1, Ordering::Relaxed) + 1;
+        debug!(
+            hits = self.hits.load(Ordering::Relaxed),
+            misses, r#"Render cache miss for "{key}""#
+        );
+        let page = render()?;
+        let rendered = Rendered {
+            html: page.html,
+            link: page.link,
+        };
+        let entry = Entry {
+            rendered: rendered.clone(),
+            deps: page
+                .deps
+                .into_iter()
+                .map(|dep| {
+                    let modified = last_modified(&dep);
+                    (dep, modified)
+                })
+                .collect(),
+        };
+        self.entries
+            .write()
+            .expect("render cache lock poisoned")
+            .insert(key.to_string(), entry);
+        Ok(rendered)
+    }
+
+    pub fn expire_at(&self, at: SystemTime) {
+        let mut expires = self.expires.write().expect("render cache lock poisoned");
+        if expires.is_none_or(|e| at < e) {
+            *expires = Some(at);
+        }
+    }
+
+    fn expire(&self) {
+        let expired = self
+            .expires
+            .read()
+            .expect("render cache lock poisoned")
+            .is_some_and(|e| e <= SystemTime::now());
+        if expired {
+            debug!("Scheduled pages changed, clearing render cache");
+            *self.expires.write().expect("render cache lock poisoned") = None;
+            self.entries
+                .write()
+                .expect("render cache lock poisoned")
+                .clear();
+        }
+    }
+
+    // Drops every page which depended on `path`
+    pub fn invalidate(&self, path: &Path) {
+        trace!(r#"Invalidating pages depending on "{}""#, path.display());
+        self.entries
+            .write()
+            .expect("render cache lock poisoned")
+            .retain(|_, entry| !entry.deps.iter().any(|(dep, _)| dep == path));
+    }
+}
+
+fn last_modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|md| md.modified()).ok()
+}
@@ -0,0 +1,112 @@
+use serde::{de, Deserialize, Deserializer};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use time_tz::{timezones, Tz};
+use tracing::{debug, info};
+
+pub type R<T> = core::result::Result<T, Error>;
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Could not read config file {}: {err}", .path.display())]
+    IO { path: PathBuf, err: std::io::Error },
+
+    #[error(transparent)]
+    Toml(#[from] toml::de::Error),
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub site: SiteConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SiteConfig {
+    pub title: String,
+    pub base_url: Option<String>,
+    pub author: Option<String>,
+    pub contact: Option<String>,
+    pub language: String,
+    pub links: Vec<Link>,
+    // Levels of the content tree in the nav. 1 lists only the top-level directories
+    pub nav_depth: usize,
+    // Entries per page of a directory listing, 0 for no limit
+    pub page_size: usize,
+    pub heading_anchors: bool,
+    pub highlight_theme: Option<String>,
+    // IANA name, such as "Europe/London". Decides when the lectionary moves to the next day
+    #[serde(deserialize_with = "time_zone")]
+    pub time_zone: Option<&'static Tz>,
+}
+
+fn time_zone<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<&'static Tz>, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    timezones::get_by_name(&name)
+        .map(Some)
+        .ok_or_else(|| de::Error::custom(format!("unknown time zone {name}")))
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Link {
+    pub name: String,
+    pub url: String,
+}
+
+impl Default for SiteConfig {
+    fn default() -> Self {
+        SiteConfig {
+            title: String::from("My Website"),
+            base_url: None,
+            author: None,
+            contact: None,
+            language: String::from("en"),
+            links: Vec::new(),
+            nav_depth: 1,
+            page_size: 0,
+            heading_anchors: false,
+            highlight_theme: None,
+            time_zone: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: impl AsRef<Path>) -> R<Self> {
+        let path = path.as_ref();
+        if !path.is_file() {
+            info!(r#"No config found at "{}", using defaults"#, path.display());
+            return Ok(Config::default());
+        }
+        debug!(r#"Loading config from "{}""#, path.display());
+        let toml = std::fs::read_to_string(path).map_err(|err| Error::IO {
+            path: path.to_path_buf(),
+            err,
+        })?;
+        Ok(toml::from_str(&toml)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time_tz::TimeZone;
+
+    #[test]
+    fn time_zones_are_resolved() {
+        let config: Config = toml::from_str("[site]\ntime_zone = \"Europe/London\"").unwrap();
+        assert_eq!(
+            config.site.time_zone.map(|tz| tz.name()),
+            Some("Europe/London")
+        );
+        let config: Config = toml::from_str("[site]").unwrap();
+        assert!(config.site.time_zone.is_none());
+    }
+
+    #[test]
+    fn unknown_time_zones_are_rejected() {
+        let err = toml::from_str::<Config>("[site]\ntime_zone = \"Mars/Olympus\"").unwrap_err();
+        assert!(err.to_string().contains("unknown time zone Mars/Olympus"));
+    }
+}
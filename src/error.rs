@@ -1,4 +1,5 @@
 use axum::{http::StatusCode, response::IntoResponse};
+use std::{io::ErrorKind, path::PathBuf};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -9,19 +10,67 @@ pub enum Error {
     Markdown(#[from] crate::markdown::Error),
     #[error(transparent)]
     Lectionary(#[from] crate::lectionary::Error),
+    #[error(transparent)]
+    Template(#[from] crate::templates::Error),
+    #[error(transparent)]
+    Config(#[from] crate::config::Error),
+    #[error(transparent)]
+    Tags(#[from] crate::tags::Error),
 
     #[error(transparent)]
     IO(#[from] std::io::Error),
 
+    #[error("Access to {} is forbidden", .0.display())]
+    Forbidden(PathBuf),
+
+    #[error("{} does not exist", .0.display())]
+    NotFound(PathBuf),
+
     #[error(transparent)]
     TokioJoinError(#[from] tokio::task::JoinError),
 
     #[error(transparent)]
     Axum(#[from] axum::http::Error),
+
+    #[error(transparent)]
+    Body(#[from] axum::Error),
+
+    #[error(transparent)]
+    Watch(#[from] notify::Error),
+}
+
+impl Error {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Error::Nav(err) => err.status(),
+            Error::Markdown(err) => err.status(),
+            Error::Lectionary(err) => err.status(),
+            Error::Template(err) => err.status(),
+            Error::Tags(err) => err.status(),
+            Error::IO(err) => io_status(err),
+            Error::Forbidden(_) => StatusCode::FORBIDDEN,
+            Error::NotFound(_) => StatusCode::NOT_FOUND,
+            Error::Config(_)
+            | Error::TokioJoinError(_)
+            | Error::Axum(_)
+            | Error::Body(_)
+            | Error::Watch(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+pub(crate) fn io_status(err: &std::io::Error) -> StatusCode {
+    match err.kind() {
+        ErrorKind::NotFound | ErrorKind::NotADirectory | ErrorKind::IsADirectory => {
+            StatusCode::NOT_FOUND
+        }
+        ErrorKind::PermissionDenied => StatusCode::FORBIDDEN,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
 }
 
 impl IntoResponse for Error {
     fn into_response(self) -> axum::response::Response {
-        (StatusCode::NOT_FOUND, format!("{self:?}")).into_response()
+        (self.status(), self.to_string()).into_response()
     }
 }
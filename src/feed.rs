@@ -0,0 +1,236 @@
+use crate::{
+    cache::Page,
+    markdown,
+    prelude::*,
+    utils::{self, escape, order::url_path, path::PathExt},
+};
+use axum::{
+    http::header,
+    response::{IntoResponse, Response},
+};
+use std::path::{Path, PathBuf};
+use time::{
+    format_description::well_known::{Rfc2822, Rfc3339},
+    OffsetDateTime,
+};
+use tracing::{debug, warn};
+
+#[derive(Debug, Clone, Copy)]
+pub enum FeedKind {
+    Rss,
+    Atom,
+}
+
+impl FeedKind {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.file_name()?.to_str()? {
+            "feed.xml" => Some(FeedKind::Rss),
+            "atom.xml" => Some(FeedKind::Atom),
+            _ => None,
+        }
+    }
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            FeedKind::Rss => "feed.xml",
+            FeedKind::Atom => "atom.xml",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            FeedKind::Rss => "application/rss+xml; charset=utf-8",
+            FeedKind::Atom => "application/atom+xml; charset=utf-8",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Item {
+    title: String,
+    link: String,
+    date: OffsetDateTime,
+    summary: Option<String>,
+    content: String,
+}
+
+struct Channel {
+    title: String,
+    link: String,
+    self_link: String,
+    updated: OffsetDateTime,
+}
+
+// Only pages with a `date` in their frontmatter are published in feeds
+pub fn feed(state: &AppState, dir: PathBuf, kind: FeedKind) -> R<Response> {
+    let key = format!("/{}", dir.join(kind.file_name()).display());
+    let xml = state
+        .cache
+        .get_or_render(&key, || render_feed(state, &dir, kind))?
+        .html;
+    Ok(([(header::CONTENT_TYPE, kind.content_type())], xml).into_response())
+}
+
+fn render_feed(state: &AppState, dir: &Path, kind: FeedKind) -> R<Page> {
+    debug!(r#"Building {kind:?} feed for "{}""#, dir.display());
+    let base_url = state.base_url();
+    let entries = utils::walk(state.root.join(dir))?
+        .into_iter()
+        .map(|p| dir.join(p))
+        .collect::<Vec<_>>();
+    // Directories change when pages are added or removed
+    let mut deps = vec![state.root.join(dir)];
+    deps.extend(
+        entries
+            .iter()
+            .map(|p| state.root.join(p))
+            .filter(|p| p.is_dir() || p.extension().is_some_and(|ext| ext == "md")),
+    );
+
+    let mut items = entries
+        .into_iter()
+        .filter(|p| p.extension().is_some_and(|ext| ext == "md") && !p.has_hidden_component())
+        .filter_map(|rel_path| {
+            let page = markdown::read_markdown(state, &rel_path)
+                .inspect_err(|err| warn!(r#"Skipping "{}": {err}"#, rel_path.display()))
+                .ok()
+                .filter(|page| state.is_visible(&page.metadata))?;
+            Some(Item {
+                date: page.metadata.date()?,
+                title: page
+                    .metadata
+                    .title
+                    .unwrap_or_else(|| state.config.site.title.clone()),
+                link: format!("{base_url}{}", markdown::page_link(&rel_path)),
+                summary: page.metadata.summary,
+                content: page.content,
+            })
+        })
+        .collect::<Vec<_>>();
+    items.sort_by_key(|item| std::cmp::Reverse(item.date));
+
+    let dir_link = match url_path(dir).to_str() {
+        Some("") | None => base_url.clone(),
+        Some(d) => format!("{base_url}/{d}"),
+    };
+    let channel = Channel {
+        title: match utils::display_name(dir) {
+            Some(name) => format!(
+                "{} · {}",
+                state.config.site.title,
+                markdown::dir_title(state, dir).unwrap_or(name)
+            ),
+            None => state.config.site.title.clone(),
+        },
+        self_link: format!("{dir_link}/{}", kind.file_name()),
+        link: dir_link,
+        updated: items
+            .first()
+            .map_or_else(OffsetDateTime::now_utc, |item| item.date),
+    };
+
+    let html = match kind {
+        FeedKind::Rss => rss(state, &channel, &items),
+        FeedKind::Atom => atom(state, &channel, &items),
+    };
+    Ok(Page {
+        html,
+        link: None,
+        deps,
+    })
+}
+
+fn rss(state: &AppState, channel: &Channel, items: &[Item]) -> String {
+    let items = items
+        .iter()
+        .map(|item| {
+            format!(
+                r#"<item><title>{}</title><link>{}</link><guid isPermaLink="true">{}</guid><pubDate>{}</pubDate><description>{}</description><content:encoded>{}</content:encoded></item>"#,
+                escape(&item.title),
+                escape(&item.link),
+                escape(&item.link),
+                item.date.format(&Rfc2822).unwrap_or_default(),
+                escape(item.summary.as_deref().unwrap_or(&item.content)),
+                escape(&item.content),
+            )
+        })
+        .collect::<String>();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?><rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom" xmlns:content="http://purl.org/rss/1.0/modules/content/"><channel><title>{}</title><link>{}</link><description>{}</description><language>{}</language><lastBuildDate>{}</lastBuildDate><atom:link href="{}" rel="self" type="application/rss+xml"/>{items}</channel></rss>"#,
+        escape(&channel.title),
+        escape(&channel.link),
+        escape(&channel.title),
+        escape(&state.config.site.language),
+        channel.updated.format(&Rfc2822).unwrap_or_default(),
+        escape(&channel.self_link),
+    )
+}
+
+fn atom(state: &AppState, channel: &Channel, items: &[Item]) -> String {
+    let entries = items
+        .iter()
+        .map(|item| {
+            let date = item.date.format(&Rfc3339).unwrap_or_default();
+            let summary = item
+                .summary
+                .as_ref()
+                .map(|s| format!("<summary>{}</summary>", escape(s)))
+                .unwrap_or_default();
+            format!(
+                r#"<entry><title>{}</title><link href="{}"/><id>{}</id><published>{date}</published><updated>{date}</updated>{summary}<content type="html">{}</content></entry>"#,
+                escape(&item.title),
+                escape(&item.link),
+                escape(&item.link),
+                escape(&item.content),
+            )
+        })
+        .collect::<String>();
+    let author = state
+        .config
+        .site
+        .author
+        .as_ref()
+        .map(|a| format!("<author><name>{}</name></author>", escape(a)))
+        .unwrap_or_default();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?><feed xmlns="http://www.w3.org/2005/Atom" xml:lang="{}" xml:base="{}/"><title>{}</title><link href="{}"/><link href="{}" rel="self"/><id>{}</id><updated>{}</updated>{author}{entries}</feed>"#,
+        escape(&state.config.site.language),
+        escape(&state.base_url()),
+        escape(&channel.title),
+        escape(&channel.link),
+        escape(&channel.self_link),
+        escape(&channel.self_link),
+        channel.updated.format(&Rfc3339).unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channels_are_titled_after_their_directory() {
+        let (_dir, state) = utils::test_site(&[
+            ("01-guide/setup.md", "# Setup"),
+            (
+                "02-notes/_index.md",
+                "```toml\ntitle = \"Field Notes\"\n```\n",
+            ),
+            ("02-notes/first.md", "# First"),
+        ]);
+        let title = |dir: &str| {
+            let xml = render_feed(&state, Path::new(dir), FeedKind::Rss)
+                .unwrap()
+                .html;
+            xml.split_once("<title>")
+                .and_then(|(_, rest)| rest.split_once("</title>"))
+                .unwrap()
+                .0
+                .to_string()
+        };
+        let site = &state.config.site.title;
+        assert_eq!(title(""), escape(site));
+        assert_eq!(title("01-guide"), escape(&format!("{site} · Guide")));
+        assert_eq!(title("02-notes"), escape(&format!("{site} · Field Notes")));
+    }
+}
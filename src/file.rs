@@ -0,0 +1,323 @@
+use crate::prelude::*;
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, response::Builder, HeaderMap, StatusCode},
+    response::Response,
+};
+use std::{
+    fs::Metadata,
+    io::{Cursor, SeekFrom},
+    ops::RangeInclusive,
+    path::PathBuf,
+    time::SystemTime,
+};
+use tokio::{
+    fs::File,
+    io::{AsyncRead, AsyncReadExt, AsyncSeekExt},
+};
+use tokio_util::io::ReaderStream;
+use tracing::trace;
+
+pub async fn get_file(
+    State(state): State<AppState>,
+    rel_path: PathBuf,
+    headers: &HeaderMap,
+) -> R<Response> {
+    trace!(r#"Serving "{}""#, rel_path.display());
+    let fs_path = state.root.join(&rel_path);
+    let mut file = File::open(&fs_path).await?;
+    let validators = Validators::new(&file.metadata().await?);
+    let content_type = content_type(&rel_path);
+
+    if validators.not_modified(headers) {
+        trace!(r#""{}" not modified"#, rel_path.display());
+        let r = validators
+            .apply(Response::builder().status(StatusCode::NOT_MODIFIED))
+            .body(Body::empty())?;
+        return Ok(r);
+    }
+
+    let ranges = headers
+        .get(header::RANGE)
+        .filter(|_| validators.if_range(headers))
+        .and_then(|range| range.to_str().ok())
+        .and_then(|range| parse_ranges(range, validators.len));
+
+    let r = match ranges.as_deref() {
+        None => validators
+            .apply(Response::builder())
+            .header(header::CONTENT_TYPE, content_type)
+            .header(header::CONTENT_LENGTH, validators.len)
+            .body(Body::from_stream(ReaderStream::new(file)))?,
+        Some([]) => {
+            trace!(r#"Unsatisfiable range for "{}""#, rel_path.display());
+            validators
+                .apply(Response::builder().status(StatusCode::RANGE_NOT_SATISFIABLE))
+                .header(header::CONTENT_RANGE, format!("bytes */{}", validators.len))
+                .body(Body::empty())?
+        }
+        Some([range]) => {
+            trace!(r#"Serving {range:?} of "{}""#, rel_path.display());
+            file.seek(SeekFrom::Start(*range.start())).await?;
+            validators
+                .apply(Response::builder().status(StatusCode::PARTIAL_CONTENT))
+                .header(header::CONTENT_TYPE, content_type)
+                .header(header::CONTENT_LENGTH, range_len(range))
+                .header(header::CONTENT_RANGE, content_range(range, validators.len))
+                .body(Body::from_stream(ReaderStream::new(
+                    file.take(range_len(range)),
+                )))?
+        }
+        Some(ranges) => {
+            trace!(
+                r#"Serving {} ranges of "{}""#,
+                ranges.len(),
+                rel_path.display()
+            );
+            let boundary = format!(
+                "webr-{:x}",
+                SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_nanos()
+            );
+            let mut len = 0;
+            let mut body: Box<dyn AsyncRead + Send + Unpin> = Box::new(tokio::io::empty());
+            for range in ranges {
+                let part_header = format!(
+                    "\r\n--{boundary}\r\nContent-Type: {content_type}\r\nContent-Range: {}\r\n\r\n",
+                    content_range(range, validators.len)
+                );
+                let mut part = File::open(&fs_path).await?;
+                part.seek(SeekFrom::Start(*range.start())).await?;
+                len += part_header.len() as u64 + range_len(range);
+                body = Box::new(
+                    body.chain(Cursor::new(part_header))
+                        .chain(part.take(range_len(range))),
+                );
+            }
+            let closing = format!("\r\n--{boundary}--\r\n");
+            len += closing.len() as u64;
+            body = Box::new(body.chain(Cursor::new(closing)));
+
+            validators
+                .apply(Response::builder().status(StatusCode::PARTIAL_CONTENT))
+                .header(
+                    header::CONTENT_TYPE,
+                    format!("multipart/byteranges; boundary={boundary}"),
+                )
+                .header(header::CONTENT_LENGTH, len)
+                .body(Body::from_stream(ReaderStream::new(body)))?
+        }
+    };
+    Ok(r)
+}
+
+// Upper limit on ranges in one request, beyond which the whole file is sent instead
+const MAX_RANGES: usize = 32;
+
+// Returns `None` if the header should be ignored and the whole file sent, or the satisfiable
+// ranges otherwise (RFC 9110 14.2). Overlapping and adjacent ranges are coalesced, so no byte is
+// sent twice
+fn parse_ranges(range: &str, len: u64) -> Option<Vec<RangeInclusive<u64>>> {
+    let specs = range
+        .trim()
+        .strip_prefix("bytes=")?
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>();
+    if specs.is_empty() {
+        return None;
+    }
+    let mut ranges = Vec::new();
+    for spec in specs {
+        let (start, end) = spec.split_once('-')?;
+        let range = match (start.trim(), end.trim()) {
+            ("", suffix) => {
+                let suffix = suffix.parse::<u64>().ok()?;
+                (suffix > 0 && len > 0).then(|| len.saturating_sub(suffix)..=len - 1)
+            }
+            (start, "") => {
+                let start = start.parse::<u64>().ok()?;
+                (start < len).then(|| start..=len - 1)
+            }
+            (start, end) => {
+                let (start, end) = (start.parse::<u64>().ok()?, end.parse::<u64>().ok()?);
+                if end < start {
+                    return None;
+                }
+                (start < len).then(|| start..=end.min(len - 1))
+            }
+        };
+        ranges.extend(range);
+    }
+
+    ranges.sort_by_key(|r| *r.start());
+    let mut coalesced: Vec<RangeInclusive<u64>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match coalesced.last_mut() {
+            Some(last) if *range.start() <= last.end().saturating_add(1) => {
+                *last = *last.start()..=*last.end().max(range.end());
+            }
+            _ => coalesced.push(range),
+        }
+    }
+    (coalesced.len() <= MAX_RANGES).then_some(coalesced)
+}
+
+fn range_len(range: &RangeInclusive<u64>) -> u64 {
+    range.end() - range.start() + 1
+}
+
+fn content_range(range: &RangeInclusive<u64>, len: u64) -> String {
+    format!("bytes {}-{}/{len}", range.start(), range.end())
+}
+
+fn content_type(path: &std::path::Path) -> String {
+    // mime_guess still uses the legacy font types
+    match path.extension().and_then(std::ffi::OsStr::to_str) {
+        Some("woff") => return "font/woff".into(),
+        Some("woff2") => return "font/woff2".into(),
+        Some("ttf") => return "font/ttf".into(),
+        Some("otf") => return "font/otf".into(),
+        _ => {}
+    }
+    let mime = mime_guess::from_path(path).first_or_octet_stream();
+    if mime.type_() == "text" {
+        format!("{mime}; charset=utf-8")
+    } else {
+        mime.to_string()
+    }
+}
+
+struct Validators {
+    len: u64,
+    etag: Option<String>,
+    last_modified: Option<SystemTime>,
+}
+
+impl Validators {
+    fn new(metadata: &Metadata) -> Self {
+        let len = metadata.len();
+        let last_modified = metadata.modified().ok();
+        let etag = last_modified
+            .and_then(|lm| lm.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .map(|d| format!(r#""{len:x}-{:x}.{:x}""#, d.as_secs(), d.subsec_nanos()));
+        Validators {
+            len,
+            etag,
+            last_modified,
+        }
+    }
+
+    fn apply(&self, mut builder: Builder) -> Builder {
+        if let Some(etag) = &self.etag {
+            builder = builder.header(header::ETAG, etag);
+        }
+        if let Some(lm) = self.last_modified {
+            builder = builder.header(header::LAST_MODIFIED, httpdate::fmt_http_date(lm));
+        }
+        builder
+            .header(header::ACCEPT_RANGES, "bytes")
+            .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+    }
+
+    // A range is only honoured if If-Range is absent or still matches the file, using the strong
+    // comparison for entity tags
+    fn if_range(&self, headers: &HeaderMap) -> bool {
+        let Some(if_range) = headers.get(header::IF_RANGE) else {
+            return true;
+        };
+        let Ok(if_range) = if_range.to_str() else {
+            return false;
+        };
+        if if_range.starts_with('"') {
+            self.etag.as_deref() == Some(if_range)
+        } else {
+            match (httpdate::parse_http_date(if_range), self.last_modified) {
+                (Ok(date), Some(lm)) => httpdate::HttpDate::from(lm) == date.into(),
+                _ => false,
+            }
+        }
+    }
+
+    // If-None-Match takes precedence over If-Modified-Since (RFC 9110 13.2.2)
+    fn not_modified(&self, headers: &HeaderMap) -> bool {
+        if let Some(inm) = headers.get(header::IF_NONE_MATCH) {
+            let Some(etag) = &self.etag else {
+                return false;
+            };
+            return inm.to_str().is_ok_and(|inm| {
+                inm.split(',')
+                    .map(str::trim)
+                    .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+            });
+        }
+
+        let since = headers
+            .get(header::IF_MODIFIED_SINCE)
+            .and_then(|ims| ims.to_str().ok())
+            .and_then(|ims| httpdate::parse_http_date(ims).ok());
+        match (since, self.last_modified) {
+            // HTTP dates only have second precision
+            (Some(since), Some(lm)) => httpdate::HttpDate::from(lm) <= since.into(),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_single_ranges() {
+        assert_eq!(parse_ranges("bytes=0-99", 1000), Some(vec![0..=99]));
+        assert_eq!(parse_ranges("bytes=900-", 1000), Some(vec![900..=999]));
+        assert_eq!(parse_ranges("bytes=-100", 1000), Some(vec![900..=999]));
+        assert_eq!(parse_ranges("bytes=-2000", 1000), Some(vec![0..=999]));
+        assert_eq!(parse_ranges("bytes=990-2000", 1000), Some(vec![990..=999]));
+    }
+
+    #[test]
+    fn unsatisfiable_ranges_are_empty() {
+        assert_eq!(parse_ranges("bytes=1000-", 1000), Some(vec![]));
+        assert_eq!(parse_ranges("bytes=-0", 1000), Some(vec![]));
+        assert_eq!(parse_ranges("bytes=0-", 0), Some(vec![]));
+    }
+
+    #[test]
+    fn invalid_or_empty_headers_are_ignored() {
+        assert_eq!(parse_ranges("bytes=", 1000), None);
+        assert_eq!(parse_ranges("bytes= , ", 1000), None);
+        assert_eq!(parse_ranges("items=0-1", 1000), None);
+        assert_eq!(parse_ranges("bytes=5-1", 1000), None);
+        assert_eq!(parse_ranges("bytes=a-b", 1000), None);
+        assert_eq!(parse_ranges("bytes=0-1,x", 1000), None);
+    }
+
+    #[test]
+    fn coalesces_overlapping_and_adjacent_ranges() {
+        let repeated = format!("bytes={}", ["0-"; MAX_RANGES].join(","));
+        assert_eq!(parse_ranges(&repeated, 1000), Some(vec![0..=999]));
+        assert_eq!(
+            parse_ranges("bytes=500-599,0-99,100-199,550-700", 1000),
+            Some(vec![0..=199, 500..=700])
+        );
+        assert_eq!(
+            parse_ranges("bytes=0-0,-1", 1000),
+            Some(vec![0..=0, 999..=999])
+        );
+    }
+
+    #[test]
+    fn too_many_ranges_are_ignored() {
+        let many = (0..=MAX_RANGES)
+            .map(|i| format!("{}-{}", i * 10, i * 10 + 1))
+            .collect::<Vec<_>>()
+            .join(",");
+        assert_eq!(parse_ranges(&format!("bytes={many}"), 10_000), None);
+    }
+}
@@ -0,0 +1,106 @@
+use super::{entries, parse_date, time_zone, Error, LecQuery, Span};
+use crate::prelude::AppState;
+use axum::{
+    extract::{rejection::QueryRejection, Path, Query, State},
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use serde_json::json;
+use tokio::task::spawn_blocking;
+
+// Takes the same filters as the HTML view, defaulting to the current year
+pub async fn lectionary_api(
+    state: State<AppState>,
+    query: Result<Query<LecQuery>, QueryRejection>,
+    headers: HeaderMap,
+) -> Response {
+    json_response(
+        spawn_blocking(move || {
+            let Query(query) = query?;
+            let today = state.today(time_zone(query.tz.as_deref(), &headers)?);
+            let (from, to) = Span::from_query(&query)?.bounds(today)?;
+            entries(from, to)
+        })
+        .await,
+    )
+}
+
+pub async fn lectionary_api_date(Path(date): Path<String>) -> Response {
+    json_response(
+        spawn_blocking(move || {
+            let date = parse_date(&date)?;
+            entries(date, date)?
+                .pop()
+                .ok_or_else(|| Error::InvalidDate(date.to_string()))
+        })
+        .await,
+    )
+}
+
+// Clients of the API expect JSON, even for errors
+fn json_response<T: Serialize>(res: Result<Result<T, Error>, tokio::task::JoinError>) -> Response {
+    match res.map_err(Error::TokioJoin).and_then(|res| res) {
+        Ok(body) => Json(body).into_response(),
+        Err(err) => (err.status(), Json(json!({ "error": err.to_string() }))).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    // The mobile app and chat bot depend on this shape
+    #[test]
+    fn entries_serialize_to_json() {
+        let lec = entries(date!(2026 - 02 - 17), date!(2026 - 02 - 18)).unwrap();
+        assert_eq!(
+            serde_json::to_value(&lec).unwrap(),
+            json!([
+                {
+                    "date": "2026-02-17",
+                    "morning": {
+                        "psalm": lec[0].morning[0],
+                        "old_testament": lec[0].morning[1],
+                        "new_testament": lec[0].morning[2],
+                    },
+                    "evening": {
+                        "psalm": lec[0].evening[0],
+                        "old_testament": lec[0].evening[1],
+                        "new_testament": lec[0].evening[2],
+                    },
+                    "feast": null,
+                },
+                {
+                    "date": "2026-02-18",
+                    "morning": {
+                        "psalm": "Ps. 38",
+                        "old_testament": "Isa. 58:1-12",
+                        "new_testament": "Luke 18:9-14",
+                    },
+                    "evening": {
+                        "psalm": "Ps. 6, 32",
+                        "old_testament": "Jonah 3",
+                        "new_testament": "1 Cor. 9:24-27",
+                    },
+                    "feast": "Ash Wednesday",
+                },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn errors_are_json() {
+        let res = lectionary_api_date(Path(String::from("2026-02-30"))).await;
+        assert_eq!(res.status(), axum::http::StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+            json!({ "error": "Invalid date: 2026-02-30" })
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use super::{calendar_days, entries, time_zone, Error, LecEntry, LecQuery, Span, R};
+use crate::{build_error_page, prelude::AppState};
+use axum::{
+    extract::{rejection::QueryRejection, Query, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+};
+use time::{macros::format_description, Date, OffsetDateTime};
+use tokio::task::spawn_blocking;
+
+// Without any parameters subscribers get the coming year, so the calendar never runs out
+pub async fn lectionary_ics(
+    state: State<AppState>,
+    query: Result<Query<LecQuery>, QueryRejection>,
+    headers: HeaderMap,
+) -> Response {
+    let st = state.0.clone();
+    spawn_blocking(move || {
+        let Query(query) = query?;
+        let today = state.today(time_zone(query.tz.as_deref(), &headers)?);
+        ics(&state, Span::from_query(&query)?, today)
+    })
+    .await
+    .map_err(Error::TokioJoin)
+    .and_then(|res| res)
+    .map(|cal| {
+        (
+            [(header::CONTENT_TYPE, "text/calendar; charset=utf-8")],
+            cal,
+        )
+            .into_response()
+    })
+    .unwrap_or_else(|err| build_error_page(&st, err.into()))
+}
+
+pub fn ics(state: &AppState, span: Span, today: Date) -> R<String> {
+    let (from, to) = match span {
+        Span::Today => calendar_days(today)?.into_inner(),
+        span => span.bounds(today)?,
+    };
+    let base_url = state.base_url();
+    let host = base_url
+        .split_once("://")
+        .map_or(base_url.as_str(), |(_, host)| host);
+    let stamp = OffsetDateTime::now_utc()
+        .format(format_description!(
+            "[year][month][day]T[hour][minute][second]Z"
+        ))
+        .expect("format error");
+
+    let mut lines = vec![
+        String::from("BEGIN:VCALENDAR"),
+        String::from("VERSION:2.0"),
+        String::from("PRODID:-//webr//Lectionary//EN"),
+        String::from("CALSCALE:GREGORIAN"),
+        String::from("METHOD:PUBLISH"),
+        format!(
+            "X-WR-CALNAME:{}",
+            escape(&format!("{} Lectionary", state.config.site.title))
+        ),
+    ];
+    for le in entries(from, to)? {
+        lines.extend(vevent(&le, &base_url, host, &stamp));
+    }
+    lines.push(String::from("END:VCALENDAR"));
+
+    Ok(lines.iter().map(|l| fold(l)).collect())
+}
+
+fn vevent(le: &LecEntry, base_url: &str, host: &str, stamp: &str) -> [String; 10] {
+    let date = ics_date(le.date);
+    let end = le
+        .date
+        .next_day()
+        .map(ics_date)
+        .unwrap_or_else(|| date.clone());
+    [
+        String::from("BEGIN:VEVENT"),
+        // The same day always has the same UID, so re-fetching updates events in place
+        format!("UID:lectionary-{date}@{host}"),
+        format!("DTSTAMP:{stamp}"),
+        format!("DTSTART;VALUE=DATE:{date}"),
+        format!("DTEND;VALUE=DATE:{end}"),
+        format!("SUMMARY:{}", escape(&summary(le))),
+        format!("DESCRIPTION:{}", escape(&description(le))),
+        format!("URL:{base_url}/lectionary/{}", le.date),
+        String::from("TRANSP:TRANSPARENT"),
+        String::from("END:VEVENT"),
+    ]
+}
+
+fn ics_date(date: Date) -> String {
+    date.format(format_description!("[year][month][day]"))
+        .expect("format error")
+}
+
+fn summary(le: &LecEntry) -> String {
+    let readings = format!(
+        "Morning: {} · Evening: {}",
+        le.morning.join("; "),
+        le.evening.join("; ")
+    );
+    match le.dscr {
+        Some(dscr) => format!("{dscr} — {readings}"),
+        None => readings,
+    }
+}
+
+fn description(le: &LecEntry) -> String {
+    let readings = format!(
+        "Morning\n{}\n\nEvening\n{}",
+        le.morning.join("\n"),
+        le.evening.join("\n")
+    );
+    match le.dscr {
+        Some(dscr) => format!("{dscr}\n\n{readings}"),
+        None => readings,
+    }
+}
+
+// RFC 5545 TEXT values
+fn escape(text: &str) -> String {
+    text.replace('\\', r"\\")
+        .replace(';', r"\;")
+        .replace(',', r"\,")
+        .replace('\n', r"\n")
+}
+
+// Lines longer than 75 octets continue on the next line after a space, without splitting any
+// characters
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 2);
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            len = 1;
+        }
+        folded.push(c);
+        len += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::lec;
+    use super::*;
+    use time::macros::date;
+
+    #[test]
+    fn escapes_text() {
+        assert_eq!(
+            escape("Ps. 6, 32; Jonah\\3\nEnd"),
+            r"Ps. 6\, 32\; Jonah\\3\nEnd"
+        );
+    }
+
+    #[test]
+    fn short_lines_are_not_folded() {
+        assert_eq!(fold("SUMMARY:short"), "SUMMARY:short\r\n");
+        let exact = format!("SUMMARY:{}", "x".repeat(75 - 8));
+        assert_eq!(fold(&exact), format!("{exact}\r\n"));
+    }
+
+    #[test]
+    fn folds_long_lines_by_octets() {
+        let line = format!("SUMMARY:{}", "é—".repeat(30));
+        let folded = fold(&line);
+        let lines = folded
+            .strip_suffix("\r\n")
+            .unwrap()
+            .split("\r\n")
+            .collect::<Vec<_>>();
+        assert!(lines.len() > 1);
+        for (i, l) in lines.iter().enumerate() {
+            assert!(l.len() <= 75, "{l}");
+            assert_eq!(l.starts_with(' '), i > 0);
+        }
+        // Unfolding gives back the original line
+        assert_eq!(
+            folded.strip_suffix("\r\n").unwrap().replace("\r\n ", ""),
+            line
+        );
+    }
+
+    #[test]
+    fn feast_day_event() {
+        let ash_wednesday = lec(2026)
+            .unwrap()
+            .into_iter()
+            .find(|le| le.date == date!(2026 - 02 - 18))
+            .unwrap();
+        assert_eq!(
+            vevent(
+                &ash_wednesday,
+                "https://example.com",
+                "example.com",
+                "20260101T000000Z"
+            ),
+            [
+                "BEGIN:VEVENT",
+                "UID:lectionary-20260218@example.com",
+                "DTSTAMP:20260101T000000Z",
+                "DTSTART;VALUE=DATE:20260218",
+                "DTEND;VALUE=DATE:20260219",
+                r"SUMMARY:Ash Wednesday — Morning: Ps. 38\; Isa. 58:1-12\; Luke 18:9-14 · Evening: Ps. 6\, 32\; Jonah 3\; 1 Cor. 9:24-27",
+                r"DESCRIPTION:Ash Wednesday\n\nMorning\nPs. 38\nIsa. 58:1-12\nLuke 18:9-14\n\nEvening\nPs. 6\, 32\nJonah 3\n1 Cor. 9:24-27",
+                "URL:https://example.com/lectionary/2026-02-18",
+                "TRANSP:TRANSPARENT",
+                "END:VEVENT",
+            ]
+        );
+    }
+
+    #[test]
+    fn events_end_the_next_day() {
+        let new_years_eve = lec(2025).unwrap().pop().unwrap();
+        let event = vevent(&new_years_eve, "", "example.com", "");
+        assert_eq!(event[3], "DTSTART;VALUE=DATE:20251231");
+        assert_eq!(event[4], "DTEND;VALUE=DATE:20260101");
+    }
+}
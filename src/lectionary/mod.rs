@@ -1,3 +1,9 @@
+mod api;
+mod ics;
+
+pub use api::{lectionary_api, lectionary_api_date};
+pub use ics::{ics, lectionary_ics};
+
 use crate::{
     build_error_page, markdown,
     prelude::*,
@@ -5,14 +11,18 @@ use crate::{
 };
 use askama::Template;
 use axum::{
-    extract::State,
+    extract::{rejection::QueryRejection, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
     response::{Html, IntoResponse, Response},
 };
 use html::tables::Table;
-use std::path::PathBuf;
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+use std::{ops::RangeInclusive, path::PathBuf};
 use thiserror::Error;
-use time::{macros::format_description, Date, Month, OffsetDateTime};
+use time::{macros::format_description, Date, Duration, Month};
+use time_tz::{timezones, Tz};
 use tokio::task::spawn_blocking;
+use tracing::debug;
 
 pub type R<T> = core::result::Result<T, Error>;
 #[derive(Debug, Error)]
@@ -27,52 +37,373 @@ pub enum Error {
 
     #[error(transparent)]
     TokioJoin(#[from] tokio::task::JoinError),
+
+    #[error("Invalid date: {0}")]
+    InvalidDate(String),
+
+    #[error("Invalid date range: {0} to {1}")]
+    InvalidRange(Date, Date),
+
+    #[error("Unknown time zone: {0}")]
+    InvalidTimeZone(String),
+
+    #[error(transparent)]
+    Query(#[from] QueryRejection),
 }
 
-#[derive(Debug)]
+impl Error {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Error::Template(err) => err.status(),
+            Error::Markdown(err) => err.status(),
+            Error::InvalidDate(_) | Error::InvalidRange(..) | Error::InvalidTimeZone(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            Error::Query(err) => err.status(),
+            Error::Easter(_) | Error::TokioJoin(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
 struct LecEntry {
-    date: Option<Date>,
+    #[serde(serialize_with = "serialize_date")]
+    date: Date,
+    #[serde(serialize_with = "serialize_readings")]
     morning: [&'static str; 3],
+    #[serde(serialize_with = "serialize_readings")]
     evening: [&'static str; 3],
+    #[serde(rename = "feast")]
     dscr: Option<&'static str>,
 }
 
-pub async fn lectionary(state: State<AppState>) -> Response {
-    let root = state.root.clone();
-    spawn_blocking(|| lectionary_wrapped(state))
-        .await
-        .map_err(Error::TokioJoin)
-        .and_then(|res| res)
-        .unwrap_or_else(|err| build_error_page(root, err.into()))
+fn serialize_date<S: Serializer>(date: &Date, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(date)
+}
+
+// Each office is a psalm, an Old Testament and a New Testament reading
+fn serialize_readings<S: Serializer>(
+    readings: &[&str; 3],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut s = serializer.serialize_struct("Readings", 3)?;
+    s.serialize_field("psalm", readings[0])?;
+    s.serialize_field("old_testament", readings[1])?;
+    s.serialize_field("new_testament", readings[2])?;
+    s.end()
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LecQuery {
+    year: Option<String>,
+    date: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    tz: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TzQuery {
+    tz: Option<String>,
 }
 
-fn lectionary_wrapped(state: State<AppState>) -> R<Response> {
-    let year = OffsetDateTime::now_utc().year();
+// A `tz` query parameter, then a `tz` cookie, can override the site's time zone. Unlike the query,
+// a bad cookie is ignored as the reader can't easily see it
+fn time_zone(query: Option<&str>, headers: &HeaderMap) -> R<Option<&'static Tz>> {
+    if let Some(tz) = query {
+        return timezones::get_by_name(tz)
+            .map(Some)
+            .ok_or_else(|| Error::InvalidTimeZone(tz.to_string()));
+    }
+    let cookie = headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|c| c.trim().split_once('='))
+        .find(|(name, _)| *name == "tz")
+        .map(|(_, value)| value);
+    Ok(cookie.and_then(|tz| {
+        let found = timezones::get_by_name(tz);
+        if found.is_none() {
+            debug!("Ignoring unknown time zone cookie {tz}");
+        }
+        found
+    }))
+}
+
+// The days to show readings for
+#[derive(Debug, Clone, Copy)]
+pub enum Span {
+    // Today's reading and the rest of this year
+    Today,
+    Year(i32),
+    Day(Date),
+    Range(Date, Date),
+}
+
+impl Span {
+    // `date` takes precedence over `from`/`to`, which take precedence over `year`. A range missing
+    // one end runs to the start or end of the other's year
+    fn from_query(query: &LecQuery) -> R<Span> {
+        if let Some(date) = &query.date {
+            return Ok(Span::Day(parse_date(date)?));
+        }
+        let from = query.from.as_deref().map(parse_date).transpose()?;
+        let to = query.to.as_deref().map(parse_date).transpose()?;
+        let year = query
+            .year
+            .as_deref()
+            .map(|y| {
+                y.trim()
+                    .parse()
+                    .map_err(|_| Error::InvalidDate(y.to_string()))
+            })
+            .transpose()?;
+        let span = match (from, to, year) {
+            (Some(from), Some(to), _) => Span::Range(from, to),
+            (Some(from), None, _) => Span::Range(from, last_day(from.year())?),
+            (None, Some(to), _) => Span::Range(first_day(to.year())?, to),
+            (None, None, Some(year)) => {
+                first_day(year)?;
+                Span::Year(year)
+            }
+            (None, None, None) => Span::Today,
+        };
+        if let Span::Range(from, to) = span {
+            if to < from || (to - from).whole_days() >= MAX_RANGE_DAYS {
+                return Err(Error::InvalidRange(from, to));
+            }
+        }
+        Ok(span)
+    }
+
+    fn bounds(self, today: Date) -> R<(Date, Date)> {
+        Ok(match self {
+            Span::Today => (first_day(today.year())?, last_day(today.year())?),
+            Span::Year(year) => (first_day(year)?, last_day(year)?),
+            Span::Day(date) => (date, date),
+            Span::Range(from, to) => (from, to),
+        })
+    }
+
+    // The spans either side of this one, as links. Days only link to other `days`
+    fn prev_next(
+        self,
+        today: Date,
+        days: &RangeInclusive<Date>,
+    ) -> [Option<(String, &'static str)>; 2] {
+        let day = |date: Option<Date>| {
+            date.filter(|d| days.contains(d))
+                .map(|d| format!("/lectionary/{d}"))
+        };
+        let year = |year: i32| {
+            first_day(year)
+                .ok()
+                .map(|_| format!("/lectionary?year={year}"))
+        };
+        match self {
+            Span::Today => [
+                day(today.previous_day()).map(|l| (l, "Previous day")),
+                day(today.next_day()).map(|l| (l, "Next day")),
+            ],
+            Span::Day(date) => [
+                day(date.previous_day()).map(|l| (l, "Previous day")),
+                day(date.next_day()).map(|l| (l, "Next day")),
+            ],
+            Span::Year(y) => [
+                year(y - 1).map(|l| (l, "Previous year")),
+                year(y + 1).map(|l| (l, "Next year")),
+            ],
+            Span::Range(from, to) => {
+                let len = to - from + Duration::DAY;
+                let range = |from: Option<Date>, to: Option<Date>| {
+                    Some(format!("/lectionary?from={}&amp;to={}", from?, to?))
+                };
+                [
+                    range(from.checked_sub(len), to.checked_sub(len)).map(|l| (l, "Previous")),
+                    range(from.checked_add(len), to.checked_add(len)).map(|l| (l, "Next")),
+                ]
+            }
+        }
+    }
+}
+
+const MAX_RANGE_DAYS: i64 = 731;
+
+// The calendar covers the coming year, and static builds write a page for each of its days
+pub fn calendar_days(today: Date) -> R<RangeInclusive<Date>> {
+    let end = today
+        .checked_add(Duration::days(364))
+        .ok_or_else(|| Error::InvalidDate(today.to_string()))?;
+    Ok(today..=end)
+}
+
+fn parse_date(date: &str) -> R<Date> {
+    Date::parse(date, format_description!("[year]-[month]-[day]"))
+        .map_err(|_| Error::InvalidDate(date.to_string()))
+}
+
+fn first_day(year: i32) -> R<Date> {
+    Date::from_calendar_date(year, Month::January, 1)
+        .map_err(|_| Error::InvalidDate(year.to_string()))
+}
+
+fn last_day(year: i32) -> R<Date> {
+    Date::from_calendar_date(year, Month::December, 31)
+        .map_err(|_| Error::InvalidDate(year.to_string()))
+}
+
+// Readings for every day from `from` to `to`, which may span several years
+fn entries(from: Date, to: Date) -> R<Vec<LecEntry>> {
+    let mut entries = Vec::new();
+    for year in from.year()..=to.year() {
+        entries.extend(
+            lec(year)?
+                .into_iter()
+                .filter(|e| e.date >= from && e.date <= to),
+        );
+    }
+    Ok(entries)
+}
+
+pub async fn lectionary(
+    state: State<AppState>,
+    query: Result<Query<LecQuery>, QueryRejection>,
+    headers: HeaderMap,
+) -> Response {
+    let st = state.0.clone();
+    spawn_blocking(move || {
+        let Query(query) = query?;
+        let today = state.today(time_zone(query.tz.as_deref(), &headers)?);
+        lectionary_wrapped(
+            state,
+            Span::from_query(&query)?,
+            today,
+            Date::MIN..=Date::MAX,
+        )
+    })
+    .await
+    .map_err(Error::TokioJoin)
+    .and_then(|res| res)
+    .unwrap_or_else(|err| build_error_page(&st, err.into()))
+}
+
+pub async fn lectionary_date(
+    state: State<AppState>,
+    Path(date): Path<String>,
+    query: Result<Query<TzQuery>, QueryRejection>,
+    headers: HeaderMap,
+) -> Response {
+    let st = state.0.clone();
+    spawn_blocking(move || {
+        let Query(query) = query?;
+        let today = state.today(time_zone(query.tz.as_deref(), &headers)?);
+        lectionary_wrapped(
+            state,
+            Span::Day(parse_date(&date)?),
+            today,
+            Date::MIN..=Date::MAX,
+        )
+    })
+    .await
+    .map_err(Error::TokioJoin)
+    .and_then(|res| res)
+    .unwrap_or_else(|err| build_error_page(&st, err.into()))
+}
+
+// `days` are the days with their own page, which the previous and next links are limited to
+pub fn lectionary_wrapped(
+    state: State<AppState>,
+    span: Span,
+    today: Date,
+    days: RangeInclusive<Date>,
+) -> R<Response> {
+    let (from, to) = span.bounds(today)?;
+    let lec = entries(from, to)?;
+
+    // Dates only need a year when it isn't in the heading
+    let (heading, f) = if from.year() == to.year() {
+        (
+            format!("Date ({})", from.year()),
+            format_description!("[day padding:none] [month repr:short]"),
+        )
+    } else {
+        (
+            String::from("Date"),
+            format_description!("[day padding:none] [month repr:short] [year]"),
+        )
+    };
+    let label = |le: &LecEntry| {
+        let dscr = match le.dscr {
+            Some(d) => format!("<br>{d}"),
+            None => String::new(),
+        };
+        format!("{}{dscr}", le.date.format(&f).expect("format error"))
+    };
+
+    let (page, md) = markdown::get_markdown_contents(&state, PathBuf::from("lectionary.md"))?;
+    // The tables go after the page's heading, or first if it has none
+    let (pre, post) = match md.split_once("</h1>") {
+        Some((pre, post)) => (format!("{pre}</h1>"), post),
+        None => (String::new(), md.as_str()),
+    };
+    let links = span
+        .prev_next(today, &days)
+        .into_iter()
+        .zip(["prev", "next"])
+        .filter_map(|(link, rel)| {
+            let (href, text) = link?;
+            Some(format!(r#"<a href="{href}" rel="{rel}">{text}</a>"#))
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    let links = format!(r#"<nav class="lectionary-nav">{links}</nav>"#);
+
+    let content = match span {
+        Span::Today | Span::Year(_) => {
+            let lec_table = table(&heading, lec.iter().map(|le| (label(le), le)));
+            // TODO: Button to collapse full lectionary table
+            let lec_today = lec
+                .iter()
+                .find(|le| le.date == today)
+                .map(|le| {
+                    let label = format!(
+                        "Today's Reading ({})",
+                        le.date.format(&f).expect("format error")
+                    );
+                    table(&heading, std::iter::once((label, le)))
+                })
+                .unwrap_or_default();
+            format!(r#"{pre}{lec_today}{links}{post}<h2>Full Lectionary</h2>{lec_table}"#)
+        }
+        Span::Day(_) | Span::Range(..) => {
+            let lec_table = table(&heading, lec.iter().map(|le| (label(le), le)));
+            format!(r#"{pre}{lec_table}{links}{post}"#)
+        }
+    };
+
+    Ok(Html(
+        page.build(&state, content)?
+            .render()
+            .map_err(templates::Error::Template)?,
+    )
+    .into_response())
+}
 
-    let lec = lec(year)?;
-    let lec_table = Table::builder()
+fn table<'a>(heading: &str, rows: impl Iterator<Item = (String, &'a LecEntry)>) -> String {
+    Table::builder()
         .table_head(|th| {
             th.table_row(|tr| {
-                tr.table_header(|thdr| thdr.text(format!("Date ({year})")));
+                tr.table_header(|thdr| thdr.text(heading.to_string()));
                 tr.table_header(|thdr| thdr.text("Morning").colspan("3"));
                 tr.table_header(|thdr| thdr.text("Evening").colspan("3"))
             })
         })
         .table_body(|tb| {
-            for le in lec.iter() {
-                let dscr = match le.dscr {
-                    Some(d) => format!("<br>{d}"),
-                    None => String::new(),
-                };
-                let date = match le.date {
-                    Some(d) => {
-                        let f = format_description!("[day padding:none] [month repr:short]");
-                        format!("{}{dscr}", d.format(&f).expect("format error"))
-                    }
-                    None => format!("No date{dscr}"),
-                };
+            for (label, le) in rows {
                 tb.table_row(|tr| {
-                    tr.table_cell(|tc| tc.text(date).class("right-border"));
+                    tr.table_cell(|tc| tc.text(label).class("right-border"));
                     tr.table_cell(|tc| tc.text(le.morning[0]));
                     tr.table_cell(|tc| tc.text(le.morning[1]));
                     tr.table_cell(|tc| tc.text(le.morning[2]).class("right-border"));
@@ -85,148 +416,66 @@ fn lectionary_wrapped(state: State<AppState>) -> R<Response> {
         })
         .class("lectionary")
         .build()
-        .to_string();
-
-    // TODO: Button to collapse full lectionary table
-    // let collapse_button = Button::builder()
-    //     .type_("button")
-    //     .class("collapsible")
-    //     .text(
-    //         Heading2::builder()
-    //             .text("Full Lectionary")
-    //             .build()
-    //             .to_string(),
-    //     )
-    //     .build()
-    //     .to_string();
-
-    let today = OffsetDateTime::now_utc().date();
-    let lec_today = Table::builder()
-        .table_head(|th| {
-            th.table_row(|tr| {
-                tr.table_header(|thdr| thdr.text(format!("Date ({year})")));
-                tr.table_header(|thdr| thdr.text("Morning").colspan("3"));
-                tr.table_header(|thdr| thdr.text("Evening").colspan("3"))
-            })
-        })
-        .table_body(|tb| {
-            tb.table_row(|tr| {
-                tr.table_cell(|tc| {
-                    let f = format_description!("[day padding:none] [month repr:short]");
-                    tc.text(format!(
-                        "Today's Reading ({})",
-                        today.format(&f).expect("format error")
-                    ))
-                    .class("right-border")
-                });
-                tr.table_cell(|tc| tc.text(lec[(today.ordinal() - 1) as usize].morning[0]));
-                tr.table_cell(|tc| tc.text(lec[(today.ordinal() - 1) as usize].morning[1]));
-                tr.table_cell(|tc| {
-                    tc.text(lec[(today.ordinal() - 1) as usize].morning[2])
-                        .class("right-border")
-                });
-                tr.table_cell(|tc| tc.text(lec[(today.ordinal() - 1) as usize].evening[0]));
-                tr.table_cell(|tc| tc.text(lec[(today.ordinal() - 1) as usize].evening[1]));
-                tr.table_cell(|tc| tc.text(lec[(today.ordinal() - 1) as usize].evening[2]))
-            })
-        })
-        .build()
-        .to_string();
-
-    let (page, md) = markdown::get_markdown_contents(&state, PathBuf::from("lectionary.md"))?;
-    let (pre, post) = md.split_once("</h1>").expect("TODO");
-    // let content = format!(r#"{pre}</h1>{lec_today}{post}{collapse_button}{lec_table}"#,);
-    let content = format!(r#"{pre}</h1>{lec_today}{post}<h2>Full Lectionary</h2>{lec_table}"#,);
-
-    Ok(Html(
-        page.build(&state.root, content)?
-            .render()
-            .map_err(templates::Error::Template)?,
-    )
-    .into_response())
+        .to_string()
 }
 
-fn lec(year: i32) -> R<Vec<LecEntry>> {
-    let mut lec = lec_skeleton(year);
-    let easter: usize = (easter(year)?.ordinal() - 1) as usize; // -1 to make 0 based
-
-    // Insert special days
-    // TODO: Fix when the days are
-    // Go backwards to get indices right?
-
-    lec.insert(
-        easter - 46,
-        LecEntry {
-            date: None,
-            morning: EASTER[0][0],
-            evening: EASTER[0][1],
-            dscr: Some("Ash Wednesday"),
-        },
-    );
-
-    lec.insert(
-        easter - 3,
-        LecEntry {
-            date: None,
-            morning: EASTER[1][0],
-            evening: EASTER[1][1],
-            dscr: Some("Maundy Thursday"),
-        },
-    );
-
-    lec.insert(
-        easter - 2,
-        LecEntry {
-            date: None,
-            morning: EASTER[2][0],
-            evening: EASTER[2][1],
-            dscr: Some("Good Friday"),
-        },
-    );
-
-    lec.insert(
-        easter - 1,
-        LecEntry {
-            date: None,
-            morning: EASTER[3][0],
-            evening: EASTER[3][1],
-            dscr: Some("Holy Saturday"),
-        },
-    );
-
-    lec.insert(
-        easter,
-        LecEntry {
-            date: None,
-            morning: EASTER[4][0],
-            evening: EASTER[4][1],
-            dscr: Some("Easter Sunday"),
-        },
-    );
+// Moveable feasts, in days from Easter, in the same order as their readings in `EASTER`
+const FEASTS: [(i64, &str); 6] = [
+    (-46, "Ash Wednesday"),
+    (-3, "Maundy Thursday"),
+    (-2, "Good Friday"),
+    (-1, "Holy Saturday"),
+    (0, "Easter Sunday"),
+    (39, "Ascension Day"),
+];
 
-    lec.insert(
-        easter + 39,
-        LecEntry {
-            date: None,
-            morning: EASTER[5][0],
-            evening: EASTER[5][1],
-            dscr: Some("Ascension Day"),
-        },
-    );
+fn lec(year: i32) -> R<Vec<LecEntry>> {
+    let easter = easter(year)?;
+    let feasts = FEASTS
+        .iter()
+        .zip(EASTER.iter())
+        .map(|((offset, dscr), [morning, evening])| {
+            (easter + Duration::days(*offset), *dscr, *morning, *evening)
+        })
+        .collect::<Vec<_>>();
 
-    // Add dates
+    // A feast takes the place of the day's reading, which moves to the next day, so the readings
+    // run out on the last day of the year
+    let mut readings = lec_skeleton(year).into_iter();
+    let mut lec = Vec::with_capacity(366);
     let mut date = Date::from_calendar_date(year, Month::January, 1)?;
-    for e in lec.iter_mut() {
-        e.date = Some(date);
-        date = date
-            .next_day()
-            .unwrap_or(Date::from_calendar_date(year, Month::January, 1)?);
+    while date.year() == year {
+        let entry = match feasts.iter().find(|(d, ..)| *d == date) {
+            Some((_, dscr, morning, evening)) => LecEntry {
+                date,
+                morning: *morning,
+                evening: *evening,
+                dscr: Some(dscr),
+            },
+            None => {
+                let (morning, evening) = readings
+                    .next()
+                    .expect("there is a reading for every day of the year");
+                LecEntry {
+                    date,
+                    morning,
+                    evening,
+                    dscr: None,
+                }
+            }
+        };
+        lec.push(entry);
+        match date.next_day() {
+            Some(next) => date = next,
+            None => break,
+        }
     }
 
     Ok(lec)
 }
 
-fn lec_skeleton(year: i32) -> Vec<LecEntry> {
+// The regular readings, without the moveable feasts
+fn lec_skeleton(year: i32) -> Vec<([&'static str; 3], [&'static str; 3])> {
     let mut l = MORNING
         .iter()
         .zip(EVENING.iter())
@@ -235,13 +484,7 @@ fn lec_skeleton(year: i32) -> Vec<LecEntry> {
             let psalm_idx = i % 30;
             let morning = [PSALM_MORNING[psalm_idx], m[0], m[1]];
             let evening = [PSALM_EVENING[psalm_idx], e[0], e[1]];
-
-            LecEntry {
-                date: None,
-                morning,
-                evening,
-                dscr: None,
-            }
+            (morning, evening)
         })
         .collect::<Vec<_>>();
     if !time::util::is_leap_year(year) {
@@ -1089,3 +1332,187 @@ static EVENING: [[&str; 2]; 360] = [
     ["Isa. 64", "2 John"],
     ["Isa. 66", "Jude"],
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+    use time_tz::TimeZone;
+
+    fn query(params: &[(&str, &str)]) -> LecQuery {
+        let mut query = LecQuery::default();
+        for (key, value) in params {
+            let value = Some(value.to_string());
+            match *key {
+                "year" => query.year = value,
+                "date" => query.date = value,
+                "from" => query.from = value,
+                "to" => query.to = value,
+                "tz" => query.tz = value,
+                _ => unreachable!(),
+            }
+        }
+        query
+    }
+
+    #[test]
+    fn easter_dates() {
+        for expected in [
+            date!(1818 - 03 - 22),
+            date!(1943 - 04 - 25),
+            date!(2000 - 04 - 23),
+            date!(2019 - 04 - 21),
+            date!(2024 - 03 - 31),
+            date!(2025 - 04 - 20),
+            date!(2038 - 04 - 25),
+            date!(2285 - 03 - 22),
+        ] {
+            assert_eq!(easter(expected.year()).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn every_day_has_one_entry() {
+        for year in 1900..=2100 {
+            let lec = lec(year).unwrap();
+            let days = if time::util::is_leap_year(year) {
+                366
+            } else {
+                365
+            };
+            assert_eq!(lec.len(), days, "{year}");
+            for (i, entry) in lec.iter().enumerate() {
+                assert_eq!(usize::from(entry.date.ordinal()), i + 1, "{year}");
+            }
+        }
+    }
+
+    #[test]
+    fn feasts_fall_on_their_dates() {
+        for year in 1900..=2100 {
+            let easter = easter(year).unwrap();
+            let lec = lec(year).unwrap();
+            for ((offset, dscr), [morning, evening]) in FEASTS.iter().zip(EASTER.iter()) {
+                let date = easter + Duration::days(*offset);
+                let entry = &lec[usize::from(date.ordinal()) - 1];
+                assert_eq!(entry.date, date, "{dscr} {year}");
+                assert_eq!(entry.dscr, Some(*dscr), "{year}");
+                assert_eq!(&entry.morning, morning, "{dscr} {year}");
+                assert_eq!(&entry.evening, evening, "{dscr} {year}");
+            }
+            assert_eq!(
+                lec.iter().filter(|e| e.dscr.is_some()).count(),
+                FEASTS.len()
+            );
+            assert_eq!(easter.weekday(), time::Weekday::Sunday);
+        }
+    }
+
+    #[test]
+    fn christmas_is_fixed() {
+        for year in 1900..=2100 {
+            let christmas = Date::from_calendar_date(year, Month::December, 25).unwrap();
+            let entry = &lec(year).unwrap()[usize::from(christmas.ordinal()) - 1];
+            assert_eq!(entry.morning[2], "Luke 2:1-14", "{year}");
+        }
+    }
+
+    #[test]
+    fn spans_from_queries() {
+        let span = |params: &[(&str, &str)]| {
+            Span::from_query(&query(params)).map(|s| s.bounds(date!(2026 - 10 - 18)))
+        };
+        let today = date!(2026 - 10 - 18);
+        assert!(matches!(Span::from_query(&query(&[])), Ok(Span::Today)));
+        assert_eq!(
+            span(&[]).unwrap().unwrap(),
+            (date!(2026 - 01 - 01), date!(2026 - 12 - 31))
+        );
+        assert_eq!(
+            span(&[("year", "2024")]).unwrap().unwrap(),
+            (date!(2024 - 01 - 01), date!(2024 - 12 - 31))
+        );
+        assert_eq!(
+            span(&[("date", "2025-04-20"), ("year", "2024")])
+                .unwrap()
+                .unwrap(),
+            (date!(2025 - 04 - 20), date!(2025 - 04 - 20))
+        );
+        assert_eq!(
+            span(&[("from", "2025-12-20"), ("to", "2026-01-10")])
+                .unwrap()
+                .unwrap(),
+            (date!(2025 - 12 - 20), date!(2026 - 01 - 10))
+        );
+        assert_eq!(
+            span(&[("from", "2025-12-20")]).unwrap().unwrap(),
+            (date!(2025 - 12 - 20), date!(2025 - 12 - 31))
+        );
+        assert_eq!(
+            span(&[("to", "2025-02-01"), ("year", "2020")])
+                .unwrap()
+                .unwrap(),
+            (date!(2025 - 01 - 01), date!(2025 - 02 - 01))
+        );
+        assert_eq!(
+            Span::Day(today)
+                .prev_next(today, &(Date::MIN..=Date::MAX))
+                .map(|l| l.map(|(href, _)| href)),
+            [
+                Some(String::from("/lectionary/2026-10-17")),
+                Some(String::from("/lectionary/2026-10-19"))
+            ]
+        );
+        // Static builds only link to the days they write
+        assert_eq!(
+            Span::Day(today)
+                .prev_next(today, &calendar_days(today).unwrap())
+                .map(|l| l.map(|(href, _)| href)),
+            [None, Some(String::from("/lectionary/2026-10-19"))]
+        );
+    }
+
+    #[test]
+    fn invalid_queries() {
+        for params in [
+            &[("year", "abc")][..],
+            &[("year", "99999999")],
+            &[("date", "2025-02-30")],
+            &[("date", "yesterday")],
+            &[("from", "2025-01-01"), ("to", "2024-01-01")],
+            &[("from", "2020-01-01"), ("to", "2025-01-01")],
+        ] {
+            let err = Span::from_query(&query(params)).unwrap_err();
+            assert_eq!(err.status(), StatusCode::BAD_REQUEST, "{params:?}");
+        }
+    }
+
+    fn cookies(cookie: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, cookie.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn time_zone_query_then_cookie() {
+        let name = |tz: R<Option<&'static Tz>>| tz.unwrap().map(|tz| tz.name());
+        let headers = cookies("theme=dark; tz=Asia/Tokyo");
+        assert_eq!(name(time_zone(None, &HeaderMap::new())), None);
+        assert_eq!(name(time_zone(None, &headers)), Some("Asia/Tokyo"));
+        assert_eq!(
+            name(time_zone(Some("America/New_York"), &headers)),
+            Some("America/New_York")
+        );
+    }
+
+    #[test]
+    fn bad_time_zones() {
+        // A bad query is reported, even with a good cookie to fall back on
+        let err = time_zone(Some("Mars/Olympus"), &cookies("tz=Asia/Tokyo")).unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        // A bad cookie is ignored in favour of the site's time zone
+        assert!(time_zone(None, &cookies("tz=Mars/Olympus"))
+            .unwrap()
+            .is_none());
+    }
+}
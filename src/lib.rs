@@ -1,29 +1,36 @@
 #![recursion_limit = "512"]
 
 pub mod app_state;
+pub mod build;
+mod cache;
+pub mod config;
 pub mod error;
+mod feed;
+mod file;
 mod lectionary;
+mod live_reload;
 mod markdown;
 pub mod prelude;
+mod search;
+mod sitemap;
+mod tags;
 mod templates;
 mod utils;
 
-use crate::{lectionary::lectionary, prelude::*};
+use crate::{lectionary::lectionary, prelude::*, utils::path::PathExt};
 use askama::Template;
 use axum::{
-    body::Body,
-    extract::{Path, State},
-    http::{Request, StatusCode, Uri},
+    extract::{rejection::QueryRejection, Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, Request, StatusCode, Uri},
     response::{Html, IntoResponse, Response},
     routing::get,
     Router, ServiceExt,
 };
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use serde::Deserialize;
 use std::{net::Ipv4Addr, path::PathBuf};
 use templates::PageTemplate;
-use tokio::{fs::File, net::TcpListener, task::spawn_blocking};
-use tokio_util::io::ReaderStream;
+use tokio::{net::TcpListener, task::spawn_blocking};
 use tower::{util::MapRequestLayer, Layer};
 use tower_http::trace::TraceLayer;
 use tracing::{debug, trace};
@@ -31,46 +38,161 @@ use tracing::{debug, trace};
 #[derive(Parser, Debug)]
 #[command(version, about)]
 pub struct Args {
-    #[arg(short, long)]
-    pub content: String,
+    /// Content root directory (required)
+    #[arg(short, long, global = true)]
+    pub content: Option<String>,
 
     #[arg(short, long, default_value_t = 14958)]
     pub port: u16,
+
+    /// Site config file [default: <CONTENT>/webr.toml]
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Show draft, scheduled and expired pages
+    #[arg(long, global = true)]
+    pub drafts: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Serve the site over HTTP (default)
+    Serve {
+        /// Watch the content for changes and reload open pages
+        #[arg(short, long)]
+        watch: bool,
+    },
+    /// Render the whole site to static files
+    Build {
+        /// Directory to write the rendered site to
+        #[arg(short, long)]
+        out: PathBuf,
+    },
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
 struct Metadata {
-    title: String,
+    title: Option<String>,
     tags: Option<Vec<String>>,
+    toc: bool,
+    anchors: Option<bool>,
+    date: Option<toml::value::Datetime>,
+    summary: Option<String>,
+    noindex: bool,
+    draft: bool,
+    publish_date: Option<toml::value::Datetime>,
+    expiry_date: Option<toml::value::Datetime>,
+    #[serde(alias = "order")]
+    weight: Option<i64>,
+    // Only read from directory index pages
+    page_size: Option<usize>,
+    sort: SortOrder,
+    layout: Layout,
+    hide: Vec<String>,
 }
 
-impl Default for Metadata {
-    fn default() -> Self {
-        Metadata {
-            title: String::from("Daniel's Website"),
-            tags: None,
-        }
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum SortOrder {
+    #[default]
+    Name,
+    NameDesc,
+    Date,
+    DateDesc,
+    Modified,
+    ModifiedDesc,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Layout {
+    #[default]
+    Grid,
+    List,
+}
+
+impl Metadata {
+    fn date(&self) -> Option<time::OffsetDateTime> {
+        self.date.as_ref().and_then(utils::to_offset_date_time)
+    }
+
+    fn publish_date(&self) -> Option<time::OffsetDateTime> {
+        self.publish_date
+            .as_ref()
+            .and_then(utils::to_offset_date_time)
+    }
+
+    fn expiry_date(&self) -> Option<time::OffsetDateTime> {
+        self.expiry_date
+            .as_ref()
+            .and_then(utils::to_offset_date_time)
+    }
+
+    // Drafts are never published, scheduled pages only between their publish and expiry dates
+    fn is_published(&self, now: time::OffsetDateTime) -> bool {
+        !self.draft
+            && self.publish_date().is_none_or(|d| d <= now)
+            && self.expiry_date().is_none_or(|d| d > now)
+    }
+
+    // When this page next appears or disappears
+    fn next_change(&self, now: time::OffsetDateTime) -> Option<time::OffsetDateTime> {
+        [self.publish_date(), self.expiry_date()]
+            .into_iter()
+            .flatten()
+            .filter(|d| *d > now)
+            .min()
     }
 }
 
 pub async fn start(state: AppState) -> R<()> {
     let listener = TcpListener::bind((Ipv4Addr::new(0, 0, 0, 0), state.port)).await?;
 
+    let _watcher = live_reload::watch(&state)?;
+    let st = state.clone();
+    spawn_blocking(move || st.search.index(&st)).await??;
+
     debug!("Creating Router");
-    let app = MapRequestLayer::new(normalize_path).layer(
-        Router::new()
-            .route("/", get(get_root))
-            .route("/{*path}", get(get_page))
-            .route("/lectionary", get(lectionary))
-            .layer(TraceLayer::new_for_http())
-            .with_state(state),
-    );
+    let mut router = Router::new()
+        .route("/", get(get_root))
+        .route("/{*path}", get(get_page))
+        .route("/lectionary", get(lectionary))
+        .route("/lectionary/{date}", get(lectionary::lectionary_date))
+        .route("/lectionary.ics", get(lectionary::lectionary_ics))
+        .route("/api/lectionary", get(lectionary::lectionary_api))
+        .route(
+            "/api/lectionary/{date}",
+            get(lectionary::lectionary_api_date),
+        )
+        .route("/sitemap.xml", get(sitemap::sitemap))
+        .route("/robots.txt", get(sitemap::robots))
+        .route("/tags", get(tags::tags))
+        .route("/tags/{tag}", get(tags::tag))
+        .route("/search", get(search::search))
+        .route("/search.json", get(search::search_json))
+        .route(markdown::highlight::STYLESHEET_PATH, get(highlight_css));
+    if state.reload.is_some() {
+        router = router.route(live_reload::EVENTS_PATH, get(live_reload::events));
+    }
+    let app = MapRequestLayer::new(normalize_path)
+        .layer(router.layer(TraceLayer::new_for_http()).with_state(state));
 
     tracing::info!("Listening on http://{}", listener.local_addr()?);
     axum::serve(listener, app.into_make_service()).await?;
     Ok(())
 }
 
+async fn highlight_css(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/css; charset=utf-8")],
+        state.highlighter.stylesheet().to_owned(),
+    )
+}
+
 fn normalize_path<B>(mut req: Request<B>) -> Request<B>
 where
     B: std::fmt::Debug,
@@ -110,50 +232,188 @@ where
     req
 }
 
-async fn get_root(state: State<AppState>) -> Response {
-    get_page(state, Path(PathBuf::new())).await
+// Every route takes this, so a query that doesn't parse is ignored rather than rejected. Only
+// directory listings complain about a bad page number
+#[derive(Debug, Default, Deserialize)]
+struct PageQuery {
+    page: Option<String>,
+}
+
+type LenientQuery = Result<Query<PageQuery>, QueryRejection>;
+
+async fn get_root(state: State<AppState>, query: LenientQuery, headers: HeaderMap) -> Response {
+    get_page(state, Path(PathBuf::new()), query, headers).await
 }
 
-async fn get_page(state: State<AppState>, Path(req_path): Path<PathBuf>) -> Response {
-    let root = state.root.clone();
-    get_page_wrapped(state, req_path)
+async fn get_page(
+    state: State<AppState>,
+    Path(req_path): Path<PathBuf>,
+    query: LenientQuery,
+    headers: HeaderMap,
+) -> Response {
+    let st = state.0.clone();
+    let query = query.map(|q| q.0).unwrap_or_default();
+    get_page_wrapped(state, req_path, query, &headers)
         .await
-        .unwrap_or_else(|err| build_error_page(root, err))
+        .unwrap_or_else(|err| build_error_page(&st, err))
 }
 
-async fn get_page_wrapped(state: State<AppState>, req_path: PathBuf) -> R<Response> {
-    let fs_path = state.root.join(&req_path);
+async fn get_page_wrapped(
+    state: State<AppState>,
+    req_path: PathBuf,
+    query: PageQuery,
+    headers: &HeaderMap,
+) -> R<Response> {
     let ext = req_path.extension().and_then(std::ffi::OsStr::to_str);
 
-    if fs_path.is_dir() {
-        spawn_blocking(|| markdown::render_dir(state, req_path))
-            .await?
-            .map_err(Error::Markdown)
-    } else if ext.is_none() {
-        spawn_blocking(move || markdown::render_markdown(state, req_path.with_extension("md")))
-            .await?
-            .map_err(Error::Markdown)
+    // Never leave the content root, and only hidden assets, such as fonts, are served from hidden
+    // paths
+    if req_path
+        .components()
+        .any(|c| !matches!(c, std::path::Component::Normal(_)))
+        || (req_path.has_hidden_component() && !utils::is_hidden_asset(&req_path))
+    {
+        return Err(Error::Forbidden(req_path));
+    }
+
+    // Numeric ordering prefixes are left out of URLs
+    let req_path = utils::order::resolve(&state.root, &req_path);
+    let fs_path = state.root.join(&req_path);
+    // Error pages are only shown for errors, and index pages only as their directory
+    let is_index = ext.is_none()
+        && req_path.file_name().is_some_and(|f| {
+            markdown::INDEX_FILES
+                .iter()
+                .any(|i| f == i.trim_end_matches(".md"))
+        });
+    if !fs_path.is_dir()
+        && (markdown::is_error_page(&req_path)
+            || (ext.is_none() && markdown::is_error_page(&req_path.with_extension("md")))
+            || is_index)
+    {
+        return Err(Error::NotFound(req_path));
+    }
+    if fs_path.is_dir() || ext.is_none() {
+        // Only paginated listings read the page number, and pages past the end are never
+        // rendered, so it can't be used to fill the cache
+        let paginated = fs_path.is_dir() && markdown::page_size(&state, &req_path) > 0;
+        let page = match query.page.filter(|_| paginated) {
+            Some(page) => page
+                .parse()
+                .map_err(|_| markdown::Error::PageOutOfRange(page))?,
+            None => 1,
+        };
+        let key = match page {
+            n if n != 1 => format!("/{}?page={n}", req_path.display()),
+            _ => format!("/{}", req_path.display()),
+        };
+        let cache = state.cache.clone();
+        let rendered = spawn_blocking(move || {
+            cache.get_or_render(&key, || {
+                if fs_path.is_dir() {
+                    markdown::render_dir(state, req_path, Some(page))
+                } else {
+                    markdown::render_markdown(state, req_path.with_extension("md"))
+                }
+            })
+        })
+        .await?
+        .map_err(Error::Markdown)?;
+        let mut res = Html(rendered.html).into_response();
+        if let Some(link) = rendered.link.and_then(|l| HeaderValue::from_str(&l).ok()) {
+            res.headers_mut().insert(header::LINK, link);
+        }
+        Ok(res)
+    } else if let Some(kind) = feed::FeedKind::from_path(&req_path).filter(|_| !fs_path.is_file()) {
+        let dir = req_path.parent().map(PathBuf::from).unwrap_or_default();
+        spawn_blocking(move || feed::feed(&state, dir, kind)).await?
+    } else if !markdown::is_page_visible(&state, &fs_path) {
+        // The source of a draft or scheduled page is as private as the page
+        Err(Error::Markdown(markdown::Error::Unpublished(req_path)))
     } else {
-        get_file(state, req_path).await
+        file::get_file(state, req_path, headers).await
     }
 }
 
-async fn get_file(State(state): State<AppState>, rel_path: PathBuf) -> R<Response> {
-    trace!(r#"Serving "{}""#, rel_path.display());
-    let file = File::open(state.root.join(rel_path)).await?;
-    let body = Body::from_stream(ReaderStream::new(file));
-    let r = Response::builder().body(body)?;
-    Ok(r)
+pub fn build_error_page(state: &AppState, err: Error) -> Response {
+    let status = err.status();
+    debug!("Responding with {status}: {err}");
+    render_error_page(state, status, &err)
+        .map(|ep| (status, Html(ep)).into_response())
+        .unwrap_or((StatusCode::INTERNAL_SERVER_ERROR, Html(FALLBACK_ERROR)).into_response())
 }
 
-pub fn build_error_page(root: impl AsRef<std::path::Path>, err: Error) -> Response {
-    PageTemplate::builder()
-        .title("Daniel's Website")
-        .build(root, format!("{ERROR_PAGE}<p>Error: {err}</p>"))
-        .and_then(|ep| Ok(ep.render()?))
-        .map(|ep| Html(ep).into_response())
-        .unwrap_or((StatusCode::INTERNAL_SERVER_ERROR, Html(FALLBACK_ERROR)).into_response())
+// Looks for `<status>.md`, then `error.md` in the content root. `{{ status }}`, `{{ reason }}` and
+// `{{ message }}` in those pages are replaced with escaped details of the error
+pub(crate) fn render_error_page(state: &AppState, status: StatusCode, err: &Error) -> R<String> {
+    let message = utils::escape(&err.to_string());
+    let reason = utils::escape(status.canonical_reason().unwrap_or_default());
+    let custom = [format!("{}.md", status.as_u16()), String::from("error.md")]
+        .into_iter()
+        .map(PathBuf::from)
+        .find(|p| state.root.join(p).is_file());
+
+    let page = match custom {
+        Some(path) => {
+            trace!(r#"Using "{}" as error page"#, path.display());
+            let (page, content) = markdown::get_markdown_contents(state, path)?;
+            let content = content
+                .replace("{{ status }}", status.as_str())
+                .replace("{{ reason }}", &reason)
+                .replace("{{ message }}", &message);
+            page.build(state, content)?
+        }
+        None => PageTemplate::builder()
+            .title(&state.config.site.title)
+            .build(state, format!("{ERROR_PAGE}<p>Error: {message}</p>"))?,
+    };
+    Ok(page.render().map_err(templates::Error::Template)?)
 }
 
 static ERROR_PAGE: &str = r#"<h1>Oops!</h1><p>Something's not right with this page</p><p>It could be a problem with the server, or the page may simply not exist.</p><p>Try navigating back to the home page by clicking the "Home" button in the navigation bar.</p>"#;
-static FALLBACK_ERROR: &str = r#"<!doctype html><html lang=en><meta charset=UTF-8><meta content="width=device-width,initial-scale=1" name=viewport><style>*,::after,::before{box-sizing:border-box;scroll-margin:5em 0 0;border-radius:.25em}:root{--max-width:80rem;--main-width:min(var(--max-width), 95vw);--fw-norm:300;--fw-bold:900;--ff-sans:"AlegreyaSans",sans-serif;--ff-mono:"Source Code Pro",monospace;--base-00:#292828;--base-01:#32302f;--base-02:#504945;--base-03:#665c54;--base-04:#bdae93;--base-06:#ddc7a1;--base-06:#ebdbb2;--base-07:#fbf1c7;--base-08:#ea6962;--base-09:#e78a4e;--base-0A:#d8a657;--base-0B:#a9b665;--base-0C:#89b482;--base-0D:#7daea3;--base-0E:#d3869b;--base-0F:#bd6f3e;--bs:0.25rem 0.25rem 0.75rem rgba(0, 0, 0, 0.25),0.125rem 0.125rem 0.25rem rgba(0, 0, 0, 0.15)}@font-face{font-family:AlegreyaSans;src:url(/.fonts/AlegreyaSans-Medium.eot);src:url(/.fonts/AlegreyaSans-Medium.woff) format("woff"),url(/.fonts/AlegreyaSans-Medium.woff2) format("woff2")}@supports (font-size:clamp(1rem,1vw,1rem)){:root{--fs--2:clamp(0.51rem, 0.23vw + 0.46rem, 0.74rem);--fs--1:clamp(0.61rem, 0.37vw + 0.54rem, 0.98rem);--fs-0:clamp(0.73rem, 0.58vw + 0.62rem, 1.31rem);--fs-1:clamp(0.88rem, 0.86vw + 0.71rem, 1.75rem);--fs-2:clamp(1.05rem, 1.27vw + 0.81rem, 2.33rem);--fs-3:clamp(1.26rem, 1.83vw + 0.92rem, 3.11rem);--fs-4:clamp(1.51rem, 2.6vw + 1.02rem, 4.15rem);--fs-5:clamp(1.81rem, 3.67vw + 1.13rem, 5.53rem)}}@supports not (font-size:clamp(1rem,1vw,1rem)){:root{--fs--2:0.51rem;--fs--1:0.61rem;--fs-0:0.73rem;--fs-1:0.88rem;--fs-2:1.05rem;--fs-3:1.26rem;--fs-4:1.51rem;--fs-5:1.81rem}@media screen and (min-width:1920px){:root{--fs--2:0.74rem;--fs--1:0.98rem;--fs-0:1.31rem;--fs-1:1.75rem;--fs-2:2.33rem;--fs-3:3.11rem;--fs-4:4.15rem;--fs-5:5.53rem}}}html{scroll-behaviour:smooth;margin:0;padding:0}body{background:var(--base-01);color:var(--base-06);font-family:var(--ff-sans);font-size:var(--fs-0);line-height:1.6;padding:0;margin:0;min-height:100vh;display:flex;flex-direction:column}main{width:var(--main-width);margin:5em auto 3em;padding:0 3em;position:relative;text-align:center}p{margin:1em 0 .5em 0}a{color:var(--base-06);opacity:1;position:relative;transition:opacity 75ms ease-in-out}a:hover{opacity:.7}h1{line-height:1;margin:1em 0 .5em 0;text-decoration:underline;margin-top:0;font-size:var(--fs-4);text-decoration-color:var(--base-08)}footer{background:var(--base-00);color:var(--base-06);text-align:center;font-size:var(--fs-1);padding:1em 0;margin:auto 0 0}footer a{color:inherit;font-size:var(--fw-bold)}footer ul{list-style:none;display:flex;justify-content:center;margin:2em 0 0;padding:0}footer ul li{margin:0 .5em}footer ul li a{padding:.5em}</style><link href=/style.css rel=stylesheet><title>Daniel's Website</title><main><h1>Fatal Error</h1><p>Something went wrong while trying to show the error page!<h2><a href=/ >Main page</a></h2></main><footer><a href=mailto:contact@daniellaing.com>contact@daniellaing.com</a><ul><li><a href=https://github.com/daniellaing , target=_blank>GitHub</a><li><a href=https://gitlab.com/Bodleum , target=_blank>GitLab</a><li><a href=https://www.instagram.com/_thebakerdan , target=_blank>Instagram</a></ul></footer>"#;
+static FALLBACK_ERROR: &str = r#"<!doctype html><html lang=en><meta charset=UTF-8><meta content="width=device-width,initial-scale=1" name=viewport><style>*,::after,::before{box-sizing:border-box;scroll-margin:5em 0 0;border-radius:.25em}:root{--max-width:80rem;--main-width:min(var(--max-width), 95vw);--fw-norm:300;--fw-bold:900;--ff-sans:"AlegreyaSans",sans-serif;--ff-mono:"Source Code Pro",monospace;--base-00:#292828;--base-01:#32302f;--base-02:#504945;--base-03:#665c54;--base-04:#bdae93;--base-06:#ddc7a1;--base-06:#ebdbb2;--base-07:#fbf1c7;--base-08:#ea6962;--base-09:#e78a4e;--base-0A:#d8a657;--base-0B:#a9b665;--base-0C:#89b482;--base-0D:#7daea3;--base-0E:#d3869b;--base-0F:#bd6f3e;--bs:0.25rem 0.25rem 0.75rem rgba(0, 0, 0, 0.25),0.125rem 0.125rem 0.25rem rgba(0, 0, 0, 0.15)}@font-face{font-family:AlegreyaSans;src:url(/.fonts/AlegreyaSans-Medium.eot);src:url(/.fonts/AlegreyaSans-Medium.woff) format("woff"),url(/.fonts/AlegreyaSans-Medium.woff2) format("woff2")}@supports (font-size:clamp(1rem,1vw,1rem)){:root{--fs--2:clamp(0.51rem, 0.23vw + 0.46rem, 0.74rem);--fs--1:clamp(0.61rem, 0.37vw + 0.54rem, 0.98rem);--fs-0:clamp(0.73rem, 0.58vw + 0.62rem, 1.31rem);--fs-1:clamp(0.88rem, 0.86vw + 0.71rem, 1.75rem);--fs-2:clamp(1.05rem, 1.27vw + 0.81rem, 2.33rem);--fs-3:clamp(1.26rem, 1.83vw + 0.92rem, 3.11rem);--fs-4:clamp(1.51rem, 2.6vw + 1.02rem, 4.15rem);--fs-5:clamp(1.81rem, 3.67vw + 1.13rem, 5.53rem)}}@supports not (font-size:clamp(1rem,1vw,1rem)){:root{--fs--2:0.51rem;--fs--1:0.61rem;--fs-0:0.73rem;--fs-1:0.88rem;--fs-2:1.05rem;--fs-3:1.26rem;--fs-4:1.51rem;--fs-5:1.81rem}@media screen and (min-width:1920px){:root{--fs--2:0.74rem;--fs--1:0.98rem;--fs-0:1.31rem;--fs-1:1.75rem;--fs-2:2.33rem;--fs-3:3.11rem;--fs-4:4.15rem;--fs-5:5.53rem}}}html{scroll-behaviour:smooth;margin:0;padding:0}body{background:var(--base-01);color:var(--base-06);font-family:var(--ff-sans);font-size:var(--fs-0);line-height:1.6;padding:0;margin:0;min-height:100vh;display:flex;flex-direction:column}main{width:var(--main-width);margin:5em auto 3em;padding:0 3em;position:relative;text-align:center}p{margin:1em 0 .5em 0}a{color:var(--base-06);opacity:1;position:relative;transition:opacity 75ms ease-in-out}a:hover{opacity:.7}h1{line-height:1;margin:1em 0 .5em 0;text-decoration:underline;margin-top:0;font-size:var(--fs-4);text-decoration-color:var(--base-08)}footer{background:var(--base-00);color:var(--base-06);text-align:center;font-size:var(--fs-1);padding:1em 0;margin:auto 0 0}footer a{color:inherit;font-size:var(--fw-bold)}footer ul{list-style:none;display:flex;justify-content:center;margin:2em 0 0;padding:0}footer ul li{margin:0 .5em}footer ul li a{padding:.5em}</style><link href=/style.css rel=stylesheet><title>Error</title><main><h1>Fatal Error</h1><p>Something went wrong while trying to show the error page!<h2><a href=/ >Main page</a></h2></main>"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    fn metadata(frontmatter: &str) -> Metadata {
+        toml::from_str(frontmatter).unwrap()
+    }
+
+    #[test]
+    fn drafts_are_never_published() {
+        let now = datetime!(2026-06-01 12:00 UTC);
+        assert!(metadata("").is_published(now));
+        assert!(!metadata("draft = true").is_published(now));
+        assert!(!metadata("draft = true\npublish_date = 2020-01-01").is_published(now));
+    }
+
+    #[test]
+    fn scheduled_pages_are_published_between_their_dates() {
+        let md = metadata("publish_date = 2026-06-01T12:00:00Z\nexpiry_date = 2026-07-01");
+        assert!(!md.is_published(datetime!(2026-06-01 11:59:59 UTC)));
+        assert!(md.is_published(datetime!(2026-06-01 12:00 UTC)));
+        assert!(md.is_published(datetime!(2026-06-30 23:59:59 UTC)));
+        assert!(!md.is_published(datetime!(2026-07-01 00:00 UTC)));
+    }
+
+    #[test]
+    fn next_change_is_the_next_date() {
+        let md = metadata("publish_date = 2026-06-01\nexpiry_date = 2026-07-01");
+        assert_eq!(
+            md.next_change(datetime!(2026-05-01 00:00 UTC)),
+            Some(datetime!(2026-06-01 00:00 UTC))
+        );
+        assert_eq!(
+            md.next_change(datetime!(2026-06-15 00:00 UTC)),
+            Some(datetime!(2026-07-01 00:00 UTC))
+        );
+        assert_eq!(md.next_change(datetime!(2026-08-01 00:00 UTC)), None);
+        assert_eq!(
+            metadata("").next_change(datetime!(2026-08-01 00:00 UTC)),
+            None
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use crate::prelude::*;
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::{convert::Infallible, time::Duration};
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+use tracing::{debug, info, warn};
+
+pub const EVENTS_PATH: &str = "/.webr/reload";
+
+// Editors tend to touch several files per save, so wait for things to settle before reloading
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+// The watcher stops when dropped
+pub fn watch(state: &AppState) -> R<Option<RecommendedWatcher>> {
+    let Some(reload) = state.reload.clone() else {
+        return Ok(None);
+    };
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = tx.send(event);
+    })?;
+    watcher.watch(&state.root, RecursiveMode::Recursive)?;
+    info!(r#"Watching "{}" for changes"#, state.root.display());
+
+    let cache = state.cache.clone();
+    tokio::spawn(async move {
+        while let Some(event) = rx.recv().await {
+            let mut changed = false;
+            let mut event = Some(event);
+            while let Some(ev) = event {
+                match ev {
+                    // Rendering pages reads files, which must not trigger a reload
+                    Ok(ev) if ev.kind.is_access() => {}
+                    Ok(ev) => {
+                        changed = true;
+                        for path in ev.paths {
+                            debug!(r#""{}" changed"#, path.display());
+                            // Adding or removing an entry changes its parent's listing
+                            if let Some(parent) = path.parent() {
+                                cache.invalidate(parent);
+                            }
+                            cache.invalidate(&path);
+                        }
+                    }
+                    Err(err) => warn!("Error watching content: {err}"),
+                }
+                event = tokio::time::timeout(DEBOUNCE, rx.recv())
+                    .await
+                    .ok()
+                    .flatten();
+            }
+            if changed {
+                debug!("Sending reload");
+                let _ = reload.send(());
+            }
+        }
+    });
+
+    Ok(Some(watcher))
+}
+
+pub async fn events(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let rx = state
+        .reload
+        .as_ref()
+        .map(|reload| reload.subscribe())
+        .expect("reload events are only routed when watching");
+    let stream =
+        BroadcastStream::new(rx).map(|_| Ok(Event::default().event("reload").data("reload")));
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
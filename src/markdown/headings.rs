@@ -0,0 +1,177 @@
+use crate::utils::escape;
+use pulldown_cmark::{CowStr, Event, HeadingLevel, Tag, TagEnd};
+use std::collections::HashSet;
+
+#[derive(Debug)]
+pub struct Heading {
+    level: HeadingLevel,
+    id: String,
+    text: String,
+}
+
+// Gives every heading an id, keeping any set with `{#id}`, and optionally appends a self-link
+pub fn anchor_headings(events: Vec<Event<'_>>, self_links: bool) -> (Vec<Event<'_>>, Vec<Heading>) {
+    let mut out = Vec::with_capacity(events.len());
+    let mut headings = Vec::new();
+    let mut used = HashSet::new();
+    // Explicit ids are the author's choice, so generated ids make way for them even when they
+    // come first
+    let reserved = events
+        .iter()
+        .filter_map(|e| match e {
+            Event::Start(Tag::Heading { id: Some(id), .. }) => Some(id.to_string()),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+    let mut events = events.into_iter();
+
+    while let Some(event) = events.next() {
+        let Event::Start(Tag::Heading {
+            level,
+            id,
+            classes,
+            attrs,
+        }) = event
+        else {
+            out.push(event);
+            continue;
+        };
+
+        let inner = events
+            .by_ref()
+            .take_while(|e| !matches!(e, Event::End(TagEnd::Heading(_))))
+            .collect::<Vec<_>>();
+        let text = inner
+            .iter()
+            .filter_map(|e| match e {
+                Event::Text(t) | Event::Code(t) => Some(t.as_ref()),
+                _ => None,
+            })
+            .collect::<String>();
+
+        let id = match id {
+            Some(id) => unique_id(id.to_string(), &mut used, &HashSet::new()),
+            None => unique_id(slugify(&text), &mut used, &reserved),
+        };
+
+        out.push(Event::Start(Tag::Heading {
+            level,
+            id: Some(CowStr::from(id.clone())),
+            classes,
+            attrs,
+        }));
+        out.extend(inner);
+        if self_links {
+            out.push(Event::InlineHtml(CowStr::from(format!(
+                r##"<a class="anchor" href="#{}" aria-hidden="true">#</a>"##,
+                escape(&id)
+            ))));
+        }
+        out.push(Event::End(TagEnd::Heading(level)));
+
+        headings.push(Heading { level, id, text });
+    }
+
+    (out, headings)
+}
+
+// Nested list of links to every heading below the page title (h1)
+pub fn toc(headings: &[Heading]) -> String {
+    let mut html = String::new();
+    let mut open: Vec<HeadingLevel> = Vec::new();
+
+    for h in headings.iter().filter(|h| h.level != HeadingLevel::H1) {
+        match open.last() {
+            None => html.push_str("<ul><li>"),
+            Some(&top) if h.level > top => html.push_str("<ul><li>"),
+            Some(_) => {
+                // Close deeper lists, unless this heading still belongs under the parent
+                while open.len() > 1 && open[open.len() - 2] >= h.level {
+                    html.push_str("</li></ul>");
+                    open.pop();
+                }
+                open.pop();
+                html.push_str("</li><li>");
+            }
+        }
+        open.push(h.level);
+        html.push_str(&format!(
+            r##"<a href="#{}">{}</a>"##,
+            escape(&h.id),
+            escape(&h.text)
+        ));
+    }
+    html.push_str(&"</li></ul>".repeat(open.len()));
+    html
+}
+
+fn slugify(text: &str) -> String {
+    let slug = text
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() {
+        String::from("section")
+    } else {
+        slug
+    }
+}
+
+fn unique_id(id: String, used: &mut HashSet<String>, reserved: &HashSet<String>) -> String {
+    let mut candidate = id.clone();
+    let mut n = 1;
+    while used.contains(&candidate) || reserved.contains(&candidate) {
+        candidate = format!("{id}-{n}");
+        n += 1;
+    }
+    used.insert(candidate.clone());
+    candidate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::{Options, Parser};
+
+    fn ids(md: &str) -> Vec<String> {
+        let events = Parser::new_ext(md, Options::ENABLE_HEADING_ATTRIBUTES).collect();
+        anchor_headings(events, false)
+            .1
+            .into_iter()
+            .map(|h| h.id)
+            .collect()
+    }
+
+    #[test]
+    fn slugifies_text() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("  Rust & C++ 2024 "), "rust-c-2024");
+        assert_eq!(slugify("Ünïcödé Title"), "ünïcödé-title");
+        assert_eq!(slugify("!!!"), "section");
+    }
+
+    #[test]
+    fn numbers_repeated_headings() {
+        assert_eq!(
+            ids("# Notes\n## Notes\n## Notes\n## Other"),
+            ["notes", "notes-1", "notes-2", "other"]
+        );
+    }
+
+    #[test]
+    fn explicit_ids_are_kept() {
+        assert_eq!(ids("## Setup\n## Install {#setup}"), ["setup-1", "setup"]);
+        assert_eq!(ids("## Install {#setup}\n## Setup"), ["setup", "setup-1"]);
+        assert_eq!(
+            ids("## Setup\n## Setup {#setup-1}\n## Setup"),
+            ["setup", "setup-1", "setup-2"]
+        );
+    }
+
+    #[test]
+    fn repeated_explicit_ids_are_numbered() {
+        assert_eq!(ids("## A {#x}\n## B {#x}"), ["x", "x-1"]);
+    }
+}
@@ -0,0 +1,274 @@
+use crate::utils::escape;
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, Tag, TagEnd};
+use std::{ops::RangeInclusive, path::Path};
+use syntect::{
+    highlighting::ThemeSet,
+    html::{css_for_theme_with_class_style, ClassStyle, ClassedHTMLGenerator},
+    parsing::{SyntaxSet, SyntaxSetBuilder},
+    util::LinesWithEndings,
+};
+use tracing::{debug, trace, warn};
+
+pub const STYLESHEET_PATH: &str = "/.webr/highlight.css";
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+const DEFAULT_THEME: &str = "InspiredGitHub";
+const EXTRA_CSS: &str = ".highlight .line.highlighted{display:inline-block;width:100%;background:rgba(255,255,0,.15)}.highlight .lineno{display:inline-block;min-width:2em;margin-right:1em;text-align:right;opacity:.5;user-select:none}";
+
+#[derive(Debug)]
+pub struct Highlighter {
+    syntaxes: SyntaxSet,
+    stylesheet: String,
+}
+
+impl Highlighter {
+    // Extra `.sublime-syntax` definitions are loaded from `<root>/.syntaxes`
+    pub fn new(root: impl AsRef<Path>, theme: Option<&str>) -> Self {
+        let syntaxes = SyntaxSet::load_defaults_newlines();
+        let extra = root.as_ref().join(".syntaxes");
+        let syntaxes = if extra.is_dir() {
+            debug!(r#"Loading syntax definitions from "{}""#, extra.display());
+            let mut builder: SyntaxSetBuilder = syntaxes.into_builder();
+            if let Err(err) = builder.add_from_folder(&extra, true) {
+                warn!("Could not load syntax definitions: {err}");
+            }
+            builder.build()
+        } else {
+            syntaxes
+        };
+
+        let themes = ThemeSet::load_defaults();
+        let theme = theme.unwrap_or(DEFAULT_THEME);
+        let theme = themes.themes.get(theme).unwrap_or_else(|| {
+            warn!(r#"Unknown highlight theme "{theme}", using "{DEFAULT_THEME}""#);
+            &themes.themes[DEFAULT_THEME]
+        });
+        let stylesheet = css_for_theme_with_class_style(theme, CLASS_STYLE)
+            .map(|css| css + EXTRA_CSS)
+            .unwrap_or_else(|err| {
+                warn!("Could not generate highlight stylesheet: {err}");
+                String::from(EXTRA_CSS)
+            });
+
+        Highlighter {
+            syntaxes,
+            stylesheet,
+        }
+    }
+
+    pub fn stylesheet(&self) -> &str {
+        &self.stylesheet
+    }
+
+    // Replaces fenced code blocks with highlighted HTML
+    pub fn highlight_code<'a>(&self, events: Vec<Event<'a>>) -> Vec<Event<'a>> {
+        let mut out = Vec::with_capacity(events.len());
+        let mut events = events.into_iter();
+
+        while let Some(event) = events.next() {
+            let Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) = event else {
+                out.push(event);
+                continue;
+            };
+            let code = events
+                .by_ref()
+                .take_while(|e| !matches!(e, Event::End(TagEnd::CodeBlock)))
+                .filter_map(|e| match e {
+                    Event::Text(t) => Some(t),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .concat();
+            out.push(Event::Html(CowStr::from(
+                self.highlight(&Fence::parse(&info), &code),
+            )));
+        }
+        out
+    }
+
+    fn highlight(&self, fence: &Fence, code: &str) -> String {
+        trace!(r#"Highlighting "{}" code block"#, fence.lang);
+        let syntax = self
+            .syntaxes
+            .find_syntax_by_token(fence.lang)
+            .unwrap_or_else(|| self.syntaxes.find_syntax_plain_text());
+        let mut generator =
+            ClassedHTMLGenerator::new_with_class_style(syntax, &self.syntaxes, CLASS_STYLE);
+        let html = LinesWithEndings::from(code)
+            .try_for_each(|line| generator.parse_html_for_line_which_includes_newline(line))
+            .map(|_| generator.finalize())
+            .unwrap_or_else(|err| {
+                warn!("Could not highlight code block: {err}");
+                escape(code)
+            });
+
+        let lines = split_lines(&html)
+            .into_iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let n = fence.linenostart + i;
+                let class = if fence.hl_lines.iter().any(|r| r.contains(&(i + 1))) {
+                    "line highlighted"
+                } else {
+                    "line"
+                };
+                let lineno = if fence.linenos {
+                    format!(r#"<span class="lineno" aria-hidden="true">{n}</span>"#)
+                } else {
+                    String::new()
+                };
+                format!(r#"<span class="{class}">{lineno}{line}</span>"#)
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        if fence.lang.is_empty() {
+            format!(r#"<pre class="highlight hl-code"><code>{lines}</code></pre>"#)
+        } else {
+            let lang = escape(fence.lang);
+            format!(
+                r#"<pre class="highlight hl-code" data-lang="{lang}"><code class="language-{lang}">{lines}</code></pre>"#
+            )
+        }
+    }
+}
+
+// Info string of a fenced code block, e.g. "rust,linenos,hl_lines=1 3-5,linenostart=10" or
+// "rust linenos hl_lines=1 3-5". Options may be separated by commas or spaces. `hl_lines` counts
+// from the first line of the block, whatever `linenostart` says the first line is numbered
+#[derive(Debug)]
+struct Fence<'a> {
+    lang: &'a str,
+    linenos: bool,
+    linenostart: usize,
+    hl_lines: Vec<RangeInclusive<usize>>,
+}
+
+impl<'a> Fence<'a> {
+    fn parse(info: &'a str) -> Self {
+        let mut opts = info
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|o| !o.is_empty())
+            .peekable();
+        let mut fence = Fence {
+            lang: opts.next().filter(|o| !o.contains('=')).unwrap_or_default(),
+            linenos: false,
+            linenostart: 1,
+            hl_lines: Vec::new(),
+        };
+        while let Some(opt) = opts.next() {
+            match opt.split_once('=') {
+                None if opt == "linenos" => fence.linenos = true,
+                Some(("linenostart", n)) => {
+                    fence.linenostart = n.trim_matches('"').parse().unwrap_or(1);
+                }
+                Some(("hl_lines", first)) => {
+                    // The ranges are themselves separated by spaces
+                    let mut ranges = vec![first];
+                    while let Some(range) = opts.next_if(|o| parse_range(o).is_some()) {
+                        ranges.push(range);
+                    }
+                    fence.hl_lines = ranges.into_iter().filter_map(parse_range).collect();
+                }
+                _ => warn!(r#"Unknown code block option "{opt}""#),
+            }
+        }
+        fence
+    }
+}
+
+fn parse_range(range: &str) -> Option<RangeInclusive<usize>> {
+    let range = range.trim_matches('"');
+    match range.split_once('-') {
+        Some((a, b)) => Some(a.parse().ok()?..=b.parse().ok()?),
+        None => range.parse().ok().map(|n| n..=n),
+    }
+}
+
+// Splits highlighted HTML into lines, closing any open spans at the end of each line and
+// reopening them on the next so every line can be wrapped on its own
+fn split_lines(html: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut open: Vec<&str> = Vec::new();
+    let mut line = String::new();
+    let mut has_text = false;
+    let mut rest = html;
+
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with("<span") {
+            let end = rest.find('>').map_or(rest.len(), |i| i + 1);
+            open.push(&rest[..end]);
+            line.push_str(&rest[..end]);
+            rest = &rest[end..];
+        } else if let Some(after) = rest.strip_prefix("</span>") {
+            open.pop();
+            line.push_str("</span>");
+            rest = after;
+        } else if c == '\n' {
+            line.push_str(&"</span>".repeat(open.len()));
+            lines.push(std::mem::replace(&mut line, open.concat()));
+            has_text = false;
+            rest = &rest[1..];
+        } else {
+            line.push(c);
+            has_text = true;
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    if has_text {
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_comma_separated_options() {
+        let fence = Fence::parse("rust,linenos,hl_lines=1 3-5,linenostart=10");
+        assert_eq!(fence.lang, "rust");
+        assert!(fence.linenos);
+        assert_eq!(fence.linenostart, 10);
+        assert_eq!(fence.hl_lines, [1..=1, 3..=5]);
+    }
+
+    #[test]
+    fn parses_space_separated_options() {
+        let fence = Fence::parse("rust hl_lines=2");
+        assert_eq!(fence.lang, "rust");
+        assert_eq!(fence.hl_lines, [2..=2]);
+
+        let fence = Fence::parse("python hl_lines=1 4-6 linenos linenostart=3");
+        assert_eq!(fence.lang, "python");
+        assert!(fence.linenos);
+        assert_eq!(fence.linenostart, 3);
+        assert_eq!(fence.hl_lines, [1..=1, 4..=6]);
+
+        let fence = Fence::parse(r#"sh, hl_lines="2 3""#);
+        assert_eq!(fence.lang, "sh");
+        assert_eq!(fence.hl_lines, [2..=2, 3..=3]);
+    }
+
+    #[test]
+    fn parses_bare_info_strings() {
+        assert_eq!(Fence::parse("").lang, "");
+        assert_eq!(Fence::parse("  toml  ").lang, "toml");
+        let fence = Fence::parse("hl_lines=2");
+        assert_eq!(fence.lang, "");
+        assert!(fence.hl_lines.is_empty());
+    }
+
+    #[test]
+    fn highlighted_lines_count_from_the_block() {
+        let highlighter = Highlighter::new(Path::new("/nonexistent"), None);
+        let html = highlighter.highlight(
+            &Fence::parse("text,linenos,linenostart=10,hl_lines=2"),
+            "one\ntwo\nthree\n",
+        );
+        let lines = html.lines().collect::<Vec<_>>();
+        assert!(lines[0].contains(">10</span>") && !lines[0].contains("highlighted"));
+        assert!(lines[1].contains(">11</span>") && lines[1].contains("highlighted"));
+        assert!(!lines[2].contains("highlighted"));
+    }
+}
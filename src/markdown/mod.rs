@@ -0,0 +1,563 @@
+mod headings;
+pub mod highlight;
+
+use crate::{
+    cache::Page,
+    prelude::*,
+    templates::{self, PageTemplate, PageTemplateBuilder},
+    utils::{
+        display_name, is_index, is_shown,
+        iterator::PartitionResult,
+        nav_deps,
+        order::{self, url_path},
+        path::PathExt,
+    },
+    Layout, Metadata,
+};
+use askama::Template;
+use axum::{extract::State, http::StatusCode};
+use pulldown_cmark::Parser;
+use pulldown_cmark_frontmatter::{Frontmatter, FrontmatterExtractor};
+use std::{
+    fs::{self, read_dir},
+    path::Path,
+    path::PathBuf,
+};
+use thiserror::Error;
+use time::OffsetDateTime;
+use tracing::{debug, error, trace, warn};
+
+pub type R<T> = core::result::Result<T, Error>;
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Could not get file root of {}", .0.display())]
+    FileRoot(PathBuf),
+
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+
+    #[error("No frontmatter found")]
+    Frontmatter,
+
+    #[error(transparent)]
+    Toml(#[from] toml::de::Error),
+
+    #[error(transparent)]
+    Template(#[from] templates::Error),
+
+    #[error(transparent)]
+    Path(#[from] std::path::StripPrefixError),
+
+    #[error("Page {0} does not exist")]
+    PageOutOfRange(String),
+
+    #[error("{} is not published", .0.display())]
+    Unpublished(PathBuf),
+}
+
+impl Error {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Error::FileRoot(_) | Error::PageOutOfRange(_) | Error::Unpublished(_) => {
+                StatusCode::NOT_FOUND
+            }
+            Error::IO(err) => crate::error::io_status(err),
+            Error::Template(err) => err.status(),
+            Error::Frontmatter | Error::Toml(_) | Error::Path(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "pic_grid.html")]
+struct PicGridTemplate {
+    img: String,
+    name: String,
+    link: String,
+    caption: String,
+}
+
+#[derive(Debug, Default)]
+struct Paths {
+    entry_path: PathBuf,
+    image_path: PathBuf,
+    description_path: PathBuf,
+    display_name: String,
+}
+
+#[derive(Debug)]
+pub struct MarkdownPage {
+    pub metadata: Metadata,
+    pub content: String,
+    pub toc: Option<String>,
+    pub last_modified: OffsetDateTime,
+}
+
+pub fn read_markdown(state: &AppState, rel_path: impl AsRef<Path>) -> R<MarkdownPage> {
+    let fs_path = state.root.join(rel_path).canonicalize()?;
+    trace!(r#"Reading "{}""#, fs_path.display());
+    let md = fs::read_to_string(&fs_path)?;
+    trace!("Creating frontmatter exctractor");
+    let mut extractor = FrontmatterExtractor::new(Parser::new_ext(&md, state.md_options));
+    trace!("Parsing markdown");
+    let events = extractor.by_ref().collect::<Vec<_>>();
+
+    let metadata = parse_metadata(extractor.frontmatter);
+
+    let anchors = metadata
+        .anchors
+        .unwrap_or(state.config.site.heading_anchors);
+    let (events, headings) = headings::anchor_headings(events, anchors);
+    let events = state.highlighter.highlight_code(events);
+    let mut content = String::new();
+    pulldown_cmark::html::push_html(&mut content, events.into_iter());
+
+    let l: OffsetDateTime = fs_path
+        .metadata()
+        .and_then(|md| md.modified())
+        .map(|lm| lm.into())
+        .unwrap_or_else(|err| {
+            error!("Could not get last modified date: {err}");
+            OffsetDateTime::now_utc()
+        });
+    Ok(MarkdownPage {
+        toc: metadata.toc.then(|| headings::toc(&headings)),
+        metadata,
+        content,
+        last_modified: l,
+    })
+}
+
+// Only parses the frontmatter, for when the page itself is not needed
+pub fn read_metadata(state: &AppState, rel_path: impl AsRef<Path>) -> R<Metadata> {
+    let fs_path = state.root.join(rel_path);
+    trace!(r#"Reading metadata of "{}""#, fs_path.display());
+    let md = fs::read_to_string(&fs_path)?;
+    let extractor = FrontmatterExtractor::new(Parser::new_ext(&md, state.md_options));
+    Ok(parse_metadata(extractor.extract()))
+}
+
+fn parse_metadata(frontmatter: Option<Frontmatter>) -> Metadata {
+    trace!("Parsing metadata");
+    let toml = frontmatter
+        .and_then(|fm| fm.code_block)
+        .map(|cb| cb.source)
+        .unwrap_or_else(|| {
+            error!(r#"No fronmatter found!"#);
+            "".into()
+        });
+
+    toml::from_str(&toml).unwrap_or_else(|err| {
+        error!("Error parsing frontmatter: {err}");
+        Metadata::default()
+    })
+}
+
+// Pages that describe the directory they're in rather than appearing in it
+pub const INDEX_FILES: [&str; 2] = ["index.md", "_index.md"];
+
+// `404.md`, `500.md`, `error.md` and the like in the content root are only shown in place of
+// an error, never as pages of their own
+pub fn is_error_page(rel_path: &Path) -> bool {
+    rel_path.parent().is_some_and(|p| p.as_os_str().is_empty())
+        && rel_path.extension().is_some_and(|ext| ext == "md")
+        && rel_path.file_root().is_some_and(|root| {
+            root == "error"
+                || root
+                    .parse::<u16>()
+                    .is_ok_and(|code| (400..600).contains(&code))
+        })
+}
+
+// The URL of a page, relative to the site. Index pages stand for their directory
+pub fn page_link(rel_path: &Path) -> String {
+    let rel_path = match rel_path.file_name() {
+        Some(f) if INDEX_FILES.iter().any(|i| f == *i) => rel_path.parent().unwrap_or(rel_path),
+        _ => rel_path,
+    };
+    format!("/{}", url_path(rel_path).display())
+}
+
+// Entries per page of a directory's listing, 0 when it isn't paginated
+pub fn page_size(state: &AppState, rel_dir: impl AsRef<Path>) -> usize {
+    index_file(state, rel_dir)
+        .filter(|index| is_page_visible(state, &state.root.join(index)))
+        .and_then(|index| read_metadata(state, index).ok())
+        .and_then(|md| md.page_size)
+        .unwrap_or(state.config.site.page_size)
+}
+
+pub fn index_file(state: &AppState, rel_dir: impl AsRef<Path>) -> Option<PathBuf> {
+    INDEX_FILES
+        .iter()
+        .map(|f| rel_dir.as_ref().join(f))
+        .find(|p| state.root.join(p).is_file())
+}
+
+pub fn dir_metadata(state: &AppState, rel_dir: impl AsRef<Path>) -> Option<Metadata> {
+    let index = index_file(state, rel_dir)?;
+    read_metadata(state, index).ok()
+}
+
+pub fn dir_title(state: &AppState, rel_dir: impl AsRef<Path>) -> Option<String> {
+    dir_metadata(state, rel_dir)?.title
+}
+
+pub fn get_markdown_contents(
+    state: &AppState,
+    rel_path: PathBuf,
+) -> R<(PageTemplateBuilder<templates::Title>, String)> {
+    let page = read_markdown(state, &rel_path)?;
+    if !state.is_visible(&page.metadata) {
+        return Err(Error::Unpublished(rel_path));
+    }
+    let unpublished = !page.metadata.is_published(OffsetDateTime::now_utc());
+    Ok((
+        PageTemplate::builder()
+            .title(
+                page.metadata
+                    .title
+                    .unwrap_or_else(|| state.config.site.title.clone()),
+            )
+            .last_modified(page.last_modified.date())
+            .tags_opt(page.metadata.tags)
+            .toc_opt(page.toc)
+            .unpublished(unpublished)
+            .path(rel_path.with_extension("")),
+        page.content,
+    ))
+}
+
+pub fn render_markdown(State(state): State<AppState>, rel_path: PathBuf) -> R<Page> {
+    debug!(r#"Serving markdown for "{}""#, rel_path.display());
+    let mut deps = vec![state.root.join(&rel_path)];
+    deps.extend(nav_deps(&state.root, state.config.site.nav_depth));
+    deps.extend(templates::breadcrumb_deps(&state.root, &rel_path));
+    let (page, content) = get_markdown_contents(&state, rel_path)?;
+    Ok(Page {
+        html: page
+            .build(&state, content)?
+            .render()
+            .map_err(templates::Error::Template)?,
+        link: None,
+        deps,
+    })
+}
+
+// Only the requested page of the listing is rendered, or all of it if `page` is `None`
+pub fn render_dir(
+    State(state): State<AppState>,
+    req_path: PathBuf,
+    page: Option<usize>,
+) -> R<Page> {
+    debug!(r#"Serving directory "{}""#, req_path.display());
+    let req_path_fs = state.root.join(&req_path).canonicalize()?;
+
+    // A draft or scheduled index is left out like any other page, so the directory is listed
+    // without it
+    let index = index_file(&state, &req_path)
+        .filter(|index| is_page_visible(&state, &state.root.join(index)))
+        .map(|index| read_markdown(&state, index))
+        .transpose()?;
+    let has_index = index.is_some();
+    let (metadata, intro, toc) = match index {
+        Some(page) => (page.metadata, page.content, page.toc),
+        None => (Metadata::default(), String::new(), None),
+    };
+
+    // Filter out only valid files
+    trace!("Formatting images");
+    let entries = read_dir(state.root.join(&req_path))?
+        .filter_map(Result::ok)
+        .filter(|e| is_shown(e).unwrap_or(false) && !is_index(e))
+        .filter(|e| !is_error_page(&req_path.join(e.file_name())))
+        .collect::<Vec<_>>();
+    // Every page is a dependency, hidden or not, so publishing a draft updates the listing
+    let page_deps = entries
+        .iter()
+        .map(fs::DirEntry::path)
+        .filter(|p| p.extension().is_some_and(|ext| ext == "md"))
+        .collect::<Vec<_>>();
+    let sorted_entries = entries
+        .into_iter()
+        .filter(|e| !is_hidden_by(&metadata, e) && is_page_visible(&state, &e.path()))
+        .map(get_paths(&state.root, &req_path))
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>();
+    // Sort
+    let sorted_entries = order::sort(&state, &req_path, metadata.sort, sorted_entries, |p| {
+        &p.entry_path
+    });
+
+    let page_size = page_size(&state, &req_path);
+    let pagination = page
+        .filter(|_| page_size > 0)
+        .map(|page| Pagination::new(page, sorted_entries.len().div_ceil(page_size).max(1)))
+        .transpose()?;
+    let sorted_entries = match pagination {
+        Some(ref p) => sorted_entries
+            .into_iter()
+            .skip((p.page - 1) * page_size)
+            .take(page_size)
+            .collect(),
+        None => sorted_entries,
+    };
+
+    // Captions and pictures may be added or removed without touching the directory itself
+    let mut deps = vec![state.root.join(&req_path)];
+    deps.extend(INDEX_FILES.map(|f| state.root.join(&req_path).join(f)));
+    deps.extend(page_deps);
+    deps.extend(nav_deps(&state.root, state.config.site.nav_depth));
+    deps.extend(templates::breadcrumb_deps(&state.root, &req_path));
+    deps.extend(order::deps(
+        &state.root,
+        &req_path,
+        sorted_entries.iter().map(|p| p.entry_path.clone()),
+    ));
+    for paths in sorted_entries.iter() {
+        deps.push(paths.description_path.clone());
+        deps.push(state.root.join(&paths.image_path));
+    }
+
+    let (imgs, links) = match metadata.layout {
+        Layout::Grid => sorted_entries
+            .into_iter()
+            .map(format_image_link(&state.root))
+            // Separate any items which failed, just show link instead
+            .partition_result(),
+        Layout::List => (Vec::new(), sorted_entries),
+    };
+
+    // Format links
+    trace!("Formatting links");
+    let links = links
+        .into_iter()
+        .map(format_links)
+        .fold(String::new(), |acc, s| acc + &s);
+
+    // Get page metadata
+    let title = metadata.title.clone().unwrap_or_else(|| {
+        display_name(&req_path).unwrap_or_else(|| state.config.site.title.clone())
+    });
+
+    let l: OffsetDateTime = req_path_fs
+        .metadata()
+        .and_then(|md| md.modified())
+        .map(|lm| lm.into())
+        .unwrap_or_else(|err| {
+            error!("Could not get last modified date: {err}");
+            OffsetDateTime::now_utc()
+        });
+    let unpublished = has_index && !metadata.is_published(OffsetDateTime::now_utc());
+    let url = format!("/{}", url_path(&req_path).display());
+    let (nav_links, link) = match pagination {
+        Some(p) => (p.nav(&url), p.link_header(&url)),
+        None => (String::new(), None),
+    };
+    let html = PageTemplate::builder()
+            .title(&title)
+            .last_modified(l.date())
+            .tags_opt(metadata.tags)
+            .toc_opt(toc)
+            .unpublished(unpublished)
+            .path(&req_path)
+            .build(
+                &state,
+                format!(
+                    r#"<div id="{}">{}<div><div class="pic-grid">{}</div><div class="links"><ul class="links-list">{}</ul></div></div>{}</div>"#,
+                    req_path.display(),
+                    // Like any other page, the index brings its own heading
+                    if has_index { intro } else { format!("<h1>{title}</h1>") },
+                    imgs.join(""),
+                    links,
+                    nav_links
+                ),
+            )?
+            .render()
+            .map_err(templates::Error::Template)?;
+    Ok(Page { html, link, deps })
+}
+
+struct Pagination {
+    page: usize,
+    pages: usize,
+}
+
+impl Pagination {
+    fn new(page: usize, pages: usize) -> R<Self> {
+        if page == 0 || page > pages {
+            return Err(Error::PageOutOfRange(page.to_string()));
+        }
+        Ok(Pagination { page, pages })
+    }
+
+    fn href(url: &str, page: usize) -> String {
+        match page {
+            1 => url.to_string(),
+            n => format!("{url}?page={n}"),
+        }
+    }
+
+    fn prev(&self) -> Option<usize> {
+        (self.page > 1).then(|| self.page - 1)
+    }
+
+    fn next(&self) -> Option<usize> {
+        (self.page < self.pages).then(|| self.page + 1)
+    }
+
+    // The first and last pages, and those either side of the current one
+    fn nav(&self, url: &str) -> String {
+        if self.pages == 1 {
+            return String::new();
+        }
+        let mut html = String::from(r#"<nav class="pagination" aria-label="Pagination"><ul>"#);
+        if let Some(prev) = self.prev() {
+            html.push_str(&format!(
+                r#"<li><a href="{}" rel="prev">Previous</a></li>"#,
+                Self::href(url, prev)
+            ));
+        }
+        let mut gap = false;
+        for n in 1..=self.pages {
+            if n == 1 || n == self.pages || n.abs_diff(self.page) <= 2 {
+                gap = false;
+                let current = if n == self.page {
+                    r#" aria-current="page""#
+                } else {
+                    ""
+                };
+                html.push_str(&format!(
+                    r#"<li><a href="{}"{current}>{n}</a></li>"#,
+                    Self::href(url, n)
+                ));
+            } else if !gap {
+                gap = true;
+                html.push_str("<li>…</li>");
+            }
+        }
+        if let Some(next) = self.next() {
+            html.push_str(&format!(
+                r#"<li><a href="{}" rel="next">Next</a></li>"#,
+                Self::href(url, next)
+            ));
+        }
+        html.push_str("</ul></nav>");
+        html
+    }
+
+    fn link_header(&self, url: &str) -> Option<String> {
+        let links = [(self.prev(), "prev"), (self.next(), "next")]
+            .into_iter()
+            .filter_map(|(page, rel)| Some(format!(r#"<{}>; rel="{rel}""#, Self::href(url, page?))))
+            .collect::<Vec<_>>();
+        (!links.is_empty()).then(|| links.join(", "))
+    }
+}
+
+// Directories are always visible, pages depending on their frontmatter
+pub fn is_page_visible(state: &AppState, fs_path: &Path) -> bool {
+    if fs_path.extension().is_none_or(|ext| ext != "md") {
+        return true;
+    }
+    let Ok(rel_path) = fs_path.strip_prefix(&state.root) else {
+        return true;
+    };
+    read_metadata(state, rel_path).map_or(true, |md| state.is_visible(&md))
+}
+
+// `hide` may name an entry by its file name or without its extension
+fn is_hidden_by(metadata: &Metadata, entry: &fs::DirEntry) -> bool {
+    let path = entry.path();
+    metadata
+        .hide
+        .iter()
+        .any(|h| entry.file_name() == h.as_str() || path.file_root().is_some_and(|root| root == h))
+}
+
+fn get_paths<'a>(
+    root: &'a PathBuf,
+    request_path: &'a PathBuf,
+) -> impl FnMut(fs::DirEntry) -> R<Paths> + 'a {
+    move |e| {
+        trace!(r#"Getting paths for "{}""#, e.path().display());
+        let entry_path = e.path().strip_prefix(root)?.to_path_buf();
+        let display_name = display_name(&entry_path).ok_or(Error::FileRoot(e.path()))?;
+        let description_path = root.join(request_path).join(format!(
+            ".{}",
+            entry_path.file_root().ok_or(Error::FileRoot(e.path()))?
+        ));
+
+        Ok(Paths {
+            image_path: entry_path.with_extension("webp"),
+            entry_path,
+            description_path,
+            display_name,
+        })
+    }
+}
+
+fn format_image_link(root: &Path) -> impl FnMut(Paths) -> Result<String, Paths> + '_ {
+    move |paths| {
+        if !root.join(&paths.image_path).is_file() {
+            trace!(r#"Could not find "{}""#, paths.image_path.display());
+            return Err(paths);
+        }
+        trace!(r#"Formatting image for "{}""#, paths.entry_path.display());
+
+        let caption = fs::read_to_string(&paths.description_path).unwrap_or_else(|err| {
+            warn!(
+                r#"No description found for "{}""#,
+                paths.entry_path.display()
+            );
+            warn!(
+                r#"Could not read "{}": {}"#,
+                paths.description_path.display(),
+                err
+            );
+            String::default()
+        });
+        let pg = PicGridTemplate {
+            name: paths.display_name.clone(),
+            img: format!("/{}", paths.image_path.display()),
+            link: format!("/{}", url_path(&paths.entry_path).display()),
+            caption,
+        };
+
+        pg.render().map_err(|err| {
+            warn!(
+                r#"Could not render template for "{}": {}"#,
+                paths.entry_path.display(),
+                err
+            );
+            paths
+        })
+    }
+}
+
+fn format_links(paths: Paths) -> String {
+    trace!(r#"Formatting link for "{}""#, paths.entry_path.display());
+    format!(
+        r#"<li><a href="/{}">{}</a></li>"#,
+        url_path(&paths.entry_path).display(),
+        paths.display_name
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_pages_link_to_their_directory() {
+        assert_eq!(page_link(Path::new("index.md")), "/");
+        assert_eq!(page_link(Path::new("01-recipes/_index.md")), "/recipes");
+        assert_eq!(
+            page_link(Path::new("01-recipes/02-soup.md")),
+            "/recipes/soup"
+        );
+        assert_eq!(page_link(Path::new("notes/indexes.md")), "/notes/indexes");
+    }
+}
@@ -4,5 +4,6 @@ pub use crate::error::Error;
 pub type R<T> = core::result::Result<T, Error>;
 
 pub use crate::app_state::AppState;
+pub use crate::config::Config;
 pub use crate::Args;
 pub use pulldown_cmark::Options;
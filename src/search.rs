@@ -0,0 +1,366 @@
+use crate::{
+    build_error_page, markdown,
+    prelude::*,
+    templates::{self, PageTemplate},
+    utils::{self, escape, path::PathExt},
+};
+use askama::Template;
+use axum::{
+    extract::{Query, State},
+    response::{Html, IntoResponse, Json, Response},
+};
+use pulldown_cmark::{Event, Parser};
+use pulldown_cmark_frontmatter::FrontmatterExtractor;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::SystemTime,
+};
+use time::OffsetDateTime;
+use tokio::task::spawn_blocking;
+use tracing::{debug, info, warn};
+
+const MAX_RESULTS: usize = 50;
+const SNIPPET_CHARS: usize = 160;
+const TITLE_WEIGHT: f32 = 5.0;
+const TAG_WEIGHT: f32 = 3.0;
+
+// The index is rebuilt whenever a page is added, removed or modified
+#[derive(Debug, Default)]
+pub struct Search {
+    index: RwLock<Option<Arc<SearchIndex>>>,
+}
+
+#[derive(Debug)]
+pub struct SearchIndex {
+    docs: Vec<Doc>,
+    terms: HashMap<String, Vec<(usize, f32)>>,
+    fingerprint: Vec<(PathBuf, Option<SystemTime>)>,
+    expires: Option<OffsetDateTime>,
+}
+
+#[derive(Debug)]
+struct Doc {
+    title: String,
+    link: String,
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Hit {
+    title: String,
+    link: String,
+    score: f32,
+    snippet: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    #[serde(default)]
+    q: String,
+}
+
+impl Search {
+    pub fn index(&self, state: &AppState) -> R<Arc<SearchIndex>> {
+        let current = self
+            .index
+            .read()
+            .expect("search index lock poisoned")
+            .clone();
+        let fingerprint = fingerprint(state)?;
+        let now = OffsetDateTime::now_utc();
+        if let Some(index) =
+            current.filter(|i| i.fingerprint == fingerprint && i.expires.is_none_or(|e| e > now))
+        {
+            return Ok(index);
+        }
+
+        let index = Arc::new(SearchIndex::build(state, fingerprint));
+        *self.index.write().expect("search index lock poisoned") = Some(index.clone());
+        Ok(index)
+    }
+}
+
+impl SearchIndex {
+    fn build(state: &AppState, fingerprint: Vec<(PathBuf, Option<SystemTime>)>) -> Self {
+        info!("Building search index");
+        let now = OffsetDateTime::now_utc();
+        let mut expires = None;
+        let mut docs = Vec::new();
+        let mut terms: HashMap<String, Vec<(usize, f32)>> = HashMap::new();
+
+        for (rel_path, _) in fingerprint.iter() {
+            let (metadata, text) = match read_plain_text(state, rel_path) {
+                Ok(page) => page,
+                Err(err) => {
+                    warn!(r#"Not indexing "{}": {err}"#, rel_path.display());
+                    continue;
+                }
+            };
+            if let Some(next) = metadata.next_change(now) {
+                expires = Some(expires.map_or(next, |e: OffsetDateTime| e.min(next)));
+            }
+            if !state.is_visible(&metadata) {
+                continue;
+            }
+            let title = metadata
+                .title
+                .unwrap_or_else(|| state.config.site.title.clone());
+
+            let mut weights: HashMap<String, f32> = HashMap::new();
+            for token in tokens(&text) {
+                *weights.entry(token.to_lowercase()).or_default() += 1.0;
+            }
+            for token in tokens(&title) {
+                *weights.entry(token.to_lowercase()).or_default() += TITLE_WEIGHT;
+            }
+            for tag in metadata.tags.iter().flatten() {
+                for token in tokens(tag) {
+                    *weights.entry(token.to_lowercase()).or_default() += TAG_WEIGHT;
+                }
+            }
+
+            let id = docs.len();
+            for (term, weight) in weights {
+                terms.entry(term).or_default().push((id, weight));
+            }
+            docs.push(Doc {
+                title,
+                link: markdown::page_link(rel_path),
+                text,
+            });
+        }
+
+        debug!("Indexed {} pages, {} terms", docs.len(), terms.len());
+        SearchIndex {
+            docs,
+            terms,
+            fingerprint,
+            expires,
+        }
+    }
+
+    pub fn search(&self, query: &str) -> Vec<Hit> {
+        let query = query_terms(query);
+        let mut scores: HashMap<usize, f32> = HashMap::new();
+        for term in query.iter() {
+            let Some(postings) = self.terms.get(term) else {
+                continue;
+            };
+            // Rarer terms count for more
+            let idf = (self.docs.len() as f32 / postings.len() as f32).ln() + 1.0;
+            for (doc, weight) in postings {
+                *scores.entry(*doc).or_default() += weight.ln_1p() * idf;
+            }
+        }
+
+        let mut hits = scores.into_iter().collect::<Vec<_>>();
+        hits.sort_by(|a, b| b.1.total_cmp(&a.1));
+        hits.into_iter()
+            .take(MAX_RESULTS)
+            .map(|(id, score)| {
+                let doc = &self.docs[id];
+                Hit {
+                    title: doc.title.clone(),
+                    link: doc.link.clone(),
+                    score,
+                    snippet: snippet(&doc.text, &query),
+                }
+            })
+            .collect()
+    }
+}
+
+pub async fn search(State(state): State<AppState>, Query(query): Query<SearchQuery>) -> Response {
+    let st = state.clone();
+    spawn_blocking(move || -> R<String> {
+        let hits = st.search.index(&st)?.search(&query.q);
+        render_results(&st, &query.q, &hits)
+    })
+    .await
+    .map_err(Error::from)
+    .and_then(|res| res)
+    .map(|html| Html(html).into_response())
+    .unwrap_or_else(|err| build_error_page(&state, err))
+}
+
+pub async fn search_json(
+    State(state): State<AppState>,
+    Query(query): Query<SearchQuery>,
+) -> Response {
+    let st = state.clone();
+    spawn_blocking(move || Ok(st.search.index(&st)?.search(&query.q)))
+        .await
+        .map_err(Error::from)
+        .and_then(|res| res)
+        .map(|hits| Json(hits).into_response())
+        .unwrap_or_else(|err| build_error_page(&state, err))
+}
+
+fn render_results(state: &AppState, query: &str, hits: &[Hit]) -> R<String> {
+    let terms = query_terms(query);
+    let results = if query.trim().is_empty() {
+        String::new()
+    } else if hits.is_empty() {
+        String::from("<p>No results found</p>")
+    } else {
+        let items = hits
+            .iter()
+            .map(|hit| {
+                format!(
+                    r#"<li><a href="{}">{}</a><p>{}</p></li>"#,
+                    escape(&hit.link),
+                    escape(&hit.title),
+                    highlight(&hit.snippet, &terms)
+                )
+            })
+            .collect::<String>();
+        format!(r#"<ol class="search-results">{items}</ol>"#)
+    };
+    let content = format!(
+        r#"<h1>Search</h1><form action="/search" method="get" role="search"><input type="search" name="q" value="{}" aria-label="Search"><button type="submit">Search</button></form>{results}"#,
+        escape(query)
+    );
+    Ok(PageTemplate::builder()
+        .title("Search")
+        .path("search")
+        .build(state, content)?
+        .render()
+        .map_err(templates::Error::Template)?)
+}
+
+fn fingerprint(state: &AppState) -> R<Vec<(PathBuf, Option<SystemTime>)>> {
+    Ok(utils::walk(&state.root)?
+        .into_iter()
+        .filter(|p| {
+            p.extension().is_some_and(|ext| ext == "md")
+                && !p.has_hidden_component()
+                && !markdown::is_error_page(p)
+                && !state.root.join(p.with_extension("")).is_dir()
+        })
+        .map(|p| {
+            let modified = fs::metadata(state.root.join(&p))
+                .and_then(|md| md.modified())
+                .ok();
+            (p, modified)
+        })
+        .collect())
+}
+
+fn read_plain_text(state: &AppState, rel_path: &PathBuf) -> R<(crate::Metadata, String)> {
+    let metadata = markdown::read_metadata(state, rel_path)?;
+    let md = fs::read_to_string(state.root.join(rel_path))?;
+    let mut extractor = FrontmatterExtractor::new(Parser::new_ext(&md, state.md_options));
+    let mut text = String::new();
+    for event in extractor.by_ref() {
+        match event {
+            Event::Text(t) | Event::Code(t) => {
+                text.push_str(&t);
+                text.push(' ');
+            }
+            Event::SoftBreak | Event::HardBreak | Event::End(_) => text.push(' '),
+            _ => {}
+        }
+    }
+    Ok((
+        metadata,
+        text.split_whitespace().collect::<Vec<_>>().join(" "),
+    ))
+}
+
+fn tokens(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+}
+
+fn query_terms(query: &str) -> HashSet<String> {
+    tokens(query).map(str::to_lowercase).collect()
+}
+
+// Plain text around the first matching word
+fn snippet(text: &str, terms: &HashSet<String>) -> String {
+    // Words borrow from `text`, so their offsets are always on a char boundary
+    let start = tokens(text)
+        .find(|word| terms.contains(&word.to_lowercase()))
+        .map_or(0, |word| word.as_ptr() as usize - text.as_ptr() as usize);
+
+    let before = text[..start]
+        .char_indices()
+        .rev()
+        .nth(SNIPPET_CHARS / 4)
+        .map_or(0, |(i, _)| i);
+    let snippet = text[before..]
+        .chars()
+        .take(SNIPPET_CHARS)
+        .collect::<String>();
+    let prefix = if before > 0 { "…" } else { "" };
+    let suffix = if before + snippet.len() < text.len() {
+        "…"
+    } else {
+        ""
+    };
+    format!("{prefix}{}{suffix}", snippet.trim())
+}
+
+// Escapes the snippet, marking every word that matched
+fn highlight(snippet: &str, terms: &HashSet<String>) -> String {
+    let mut html = String::new();
+    let mut word = String::new();
+    let flush = |word: &mut String, html: &mut String| {
+        if terms.contains(&word.to_lowercase()) {
+            html.push_str(&format!("<mark>{}</mark>", escape(word)));
+        } else {
+            html.push_str(&escape(word));
+        }
+        word.clear();
+    };
+    for c in snippet.chars() {
+        if c.is_alphanumeric() {
+            word.push(c);
+        } else {
+            flush(&mut word, &mut html);
+            html.push_str(&escape(c.encode_utf8(&mut [0; 4])));
+        }
+    }
+    flush(&mut word, &mut html);
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snippet_starts_at_first_match() {
+        let text = format!("{} needle haystack", "word ".repeat(100));
+        let snippet = snippet(&text, &query_terms("needle"));
+        assert!(snippet.starts_with('…'));
+        assert!(snippet.contains("needle"));
+        assert!(snippet.chars().count() <= SNIPPET_CHARS + 2);
+    }
+
+    #[test]
+    fn snippet_handles_multi_byte_separators() {
+        let text = "“Hello” — it’s\u{a0}a “quoted” world’s end—fin";
+        for query in ["hello", "quoted", "world", "fin", "missing"] {
+            let snippet = snippet(text, &query_terms(query));
+            if query != "missing" {
+                assert!(snippet.to_lowercase().contains(query), "{query}");
+            }
+        }
+
+        let long = format!("{}“Hello”", "—“’".repeat(100));
+        assert!(snippet(&long, &query_terms("hello")).contains("Hello"));
+    }
+
+    #[test]
+    fn highlight_marks_matches_and_escapes() {
+        assert_eq!(
+            highlight("Fish & “chips” and FISH", &query_terms("fish")),
+            "<mark>Fish</mark> &amp; “chips” and <mark>FISH</mark>"
+        );
+    }
+}
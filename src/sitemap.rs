@@ -0,0 +1,148 @@
+use crate::{
+    build_error_page, file, markdown,
+    prelude::*,
+    utils::{self, escape, order::url_path, path::PathExt, percent_encode},
+};
+use axum::{
+    extract::State,
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+};
+use std::path::{Path, PathBuf};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use tokio::task::spawn_blocking;
+use tracing::{debug, trace, warn};
+
+pub async fn sitemap(State(state): State<AppState>) -> Response {
+    let st = state.clone();
+    spawn_blocking(move || sitemap_xml(&st))
+        .await
+        .map_err(Error::from)
+        .and_then(|res| res)
+        .map(|xml| {
+            (
+                [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+                xml,
+            )
+                .into_response()
+        })
+        .unwrap_or_else(|err| build_error_page(&state, err))
+}
+
+// A `robots.txt` in the content root replaces the generated one
+pub async fn robots(state: State<AppState>, headers: HeaderMap) -> Response {
+    if state.root.join("robots.txt").is_file() {
+        let st = state.0.clone();
+        return file::get_file(state, PathBuf::from("robots.txt"), &headers)
+            .await
+            .unwrap_or_else(|err| build_error_page(&st, err));
+    }
+    (
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        robots_txt(&state),
+    )
+        .into_response()
+}
+
+pub fn robots_txt(state: &AppState) -> String {
+    format!(
+        "User-agent: *\nAllow: /\n\nSitemap: {}/sitemap.xml\n",
+        state.base_url()
+    )
+}
+
+// Lists every visible directory and page, except those marked `noindex` or not published
+pub fn sitemap_xml(state: &AppState) -> R<String> {
+    debug!("Building sitemap");
+    let base_url = state.base_url();
+    let mut urls = vec![url(&base_url, Path::new(""), &state.root)];
+
+    for rel_path in utils::walk(&state.root)? {
+        if rel_path.has_hidden_component() {
+            continue;
+        }
+        let fs_path = state.root.join(&rel_path);
+        if fs_path.is_dir() {
+            urls.push(url(&base_url, &url_path(&rel_path), &fs_path));
+        } else if rel_path.extension().is_some_and(|ext| ext == "md") {
+            // Shadowed by a directory of the same name, already covered by its directory, or an
+            // error page
+            if state.root.join(rel_path.with_extension("")).is_dir()
+                || rel_path
+                    .file_name()
+                    .is_some_and(|f| markdown::INDEX_FILES.iter().any(|i| f == *i))
+                || markdown::is_error_page(&rel_path)
+            {
+                continue;
+            }
+            match markdown::read_metadata(state, &rel_path) {
+                Ok(md) if md.noindex || !state.is_visible(&md) => {
+                    trace!(r#"Leaving "{}" out of sitemap"#, rel_path.display());
+                }
+                Ok(_) => urls.push(url(&base_url, &url_path(&rel_path), &fs_path)),
+                Err(err) => warn!(r#"Skipping "{}": {err}"#, rel_path.display()),
+            }
+        }
+    }
+
+    Ok(format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?><urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">{}</urlset>"#,
+        urls.concat()
+    ))
+}
+
+fn url(base_url: &str, url_path: &Path, fs_path: &Path) -> String {
+    let lastmod = fs_path
+        .metadata()
+        .and_then(|md| md.modified())
+        .ok()
+        .and_then(|lm| OffsetDateTime::from(lm).format(&Rfc3339).ok())
+        .map(|lm| format!("<lastmod>{lm}</lastmod>"))
+        .unwrap_or_default();
+    let path = percent_encode(&url_path.to_string_lossy());
+    format!(
+        "<url><loc>{}</loc>{lastmod}</url>",
+        escape(&format!("{base_url}/{path}"))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locs_are_encoded_and_escaped() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            url(
+                "https://example.com",
+                Path::new("fish & chips/café menu"),
+                dir.path()
+            )
+            .split("<lastmod>")
+            .next()
+            .unwrap(),
+            "<url><loc>https://example.com/fish%20%26%20chips/caf%C3%A9%20menu</loc>"
+        );
+    }
+
+    #[test]
+    fn error_pages_are_left_out() {
+        let (_dir, state) = utils::test_site(&[
+            ("404.md", "# Page"),
+            ("500.md", "# Page"),
+            ("error.md", "# Page"),
+            ("about.md", "# Page"),
+            ("notes/404.md", "# Page"),
+            ("1999.md", "# Page"),
+        ]);
+        let xml = sitemap_xml(&state).unwrap();
+        let loc = |path: &str| format!("<loc>{}/{path}</loc>", state.base_url());
+        for missing in ["404", "500", "error"] {
+            assert!(!xml.contains(&loc(missing)), "{missing}");
+        }
+        for present in ["about", "notes/404", "1999"] {
+            assert!(xml.contains(&loc(present)), "{present}");
+        }
+    }
+}
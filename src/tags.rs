@@ -0,0 +1,237 @@
+use crate::{
+    build_error_page, markdown,
+    prelude::*,
+    templates::{self, PageTemplate},
+    utils::{self, escape, path::PathExt, percent_encode},
+};
+use askama::Template;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use std::{cmp::Reverse, collections::BTreeMap};
+use thiserror::Error;
+use time::Date;
+use tokio::task::spawn_blocking;
+use tracing::{debug, warn};
+
+pub type R<T> = core::result::Result<T, Error>;
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(r#"No pages are tagged "{0}""#)]
+    UnknownTag(String),
+
+    #[error(transparent)]
+    Nav(#[from] utils::Error),
+
+    #[error(transparent)]
+    Template(#[from] templates::Error),
+
+    #[error(transparent)]
+    TokioJoin(#[from] tokio::task::JoinError),
+}
+
+impl Error {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Error::UnknownTag(_) => StatusCode::NOT_FOUND,
+            Error::Nav(err) => err.status(),
+            Error::Template(err) => err.status(),
+            Error::TokioJoin(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Tag {
+    pub name: String,
+    pub pages: Vec<TaggedPage>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TaggedPage {
+    pub title: String,
+    pub link: String,
+    pub date: Option<Date>,
+}
+
+// Lowercase words joined by `-`. Only letters and digits survive, but they may be non-ASCII
+pub fn slug(tag: &str) -> String {
+    tag.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+// Tags without any letters or digits have no page
+pub fn link(tag: &str) -> Option<String> {
+    let slug = slug(tag);
+    (!slug.is_empty()).then(|| format!("/tags/{}", percent_encode(&slug)))
+}
+
+// Every tag in the content root, keyed by slug
+pub fn index(state: &AppState) -> R<BTreeMap<String, Tag>> {
+    debug!("Building tag index");
+    let mut tags = BTreeMap::<String, Tag>::new();
+    for rel_path in utils::walk(&state.root)? {
+        if rel_path.has_hidden_component()
+            || rel_path.extension().is_none_or(|ext| ext != "md")
+            || markdown::is_error_page(&rel_path)
+            || state.root.join(rel_path.with_extension("")).is_dir()
+        {
+            continue;
+        }
+        let metadata = match markdown::read_metadata(state, &rel_path) {
+            Ok(md) if !state.is_visible(&md) => continue,
+            Ok(md) => md,
+            Err(err) => {
+                warn!(r#"Skipping "{}": {err}"#, rel_path.display());
+                continue;
+            }
+        };
+        let page = TaggedPage {
+            date: metadata.date().map(|d| d.date()),
+            title: metadata
+                .title
+                .clone()
+                .unwrap_or_else(|| state.config.site.title.clone()),
+            link: markdown::page_link(&rel_path),
+        };
+        for tag in metadata.tags.unwrap_or_default() {
+            if slug(&tag).is_empty() {
+                warn!(r#"Skipping tag "{tag}" of "{}""#, rel_path.display());
+                continue;
+            }
+            let entry = tags.entry(slug(&tag)).or_insert_with(|| Tag {
+                name: tag,
+                pages: Vec::new(),
+            });
+            entry.pages.push(page.clone());
+        }
+    }
+
+    for tag in tags.values_mut() {
+        tag.pages.sort_by(|a, b| {
+            Reverse(a.date)
+                .cmp(&Reverse(b.date))
+                .then_with(|| natord::compare(&a.title, &b.title))
+        });
+    }
+    Ok(tags)
+}
+
+pub async fn tags(State(state): State<AppState>) -> Response {
+    let st = state.clone();
+    spawn_blocking(move || render_tags(&st, &index(&st)?))
+        .await
+        .map_err(Error::from)
+        .and_then(|res| res)
+        .map(|html| Html(html).into_response())
+        .unwrap_or_else(|err| build_error_page(&state, err.into()))
+}
+
+pub async fn tag(State(state): State<AppState>, Path(tag): Path<String>) -> Response {
+    let st = state.clone();
+    spawn_blocking(move || render_tag(&st, &index(&st)?, &tag))
+        .await
+        .map_err(Error::from)
+        .and_then(|res| res)
+        .map(|html| Html(html).into_response())
+        .unwrap_or_else(|err| build_error_page(&state, err.into()))
+}
+
+pub fn render_tags(state: &AppState, index: &BTreeMap<String, Tag>) -> R<String> {
+    let mut tags = index.values().collect::<Vec<_>>();
+    tags.sort_by(|a, b| natord::compare_ignore_case(&a.name, &b.name));
+    let links = tags
+        .iter()
+        .filter_map(|tag| {
+            Some(format!(
+                r#"<li><a href="{}">{}</a> ({})</li>"#,
+                escape(&link(&tag.name)?),
+                escape(&tag.name),
+                tag.pages.len()
+            ))
+        })
+        .collect::<String>();
+    Ok(PageTemplate::builder()
+        .title("Tags")
+        .path("tags")
+        .build(
+            state,
+            format!(r#"<h1>Tags</h1><ul class="tags-list">{links}</ul>"#),
+        )?
+        .render()
+        .map_err(templates::Error::Template)?)
+}
+
+pub fn render_tag(state: &AppState, index: &BTreeMap<String, Tag>, tag: &str) -> R<String> {
+    let slug = slug(tag);
+    let tag = index
+        .get(&slug)
+        .ok_or_else(|| Error::UnknownTag(tag.to_string()))?;
+    let pages = tag
+        .pages
+        .iter()
+        .map(|page| {
+            let date = page
+                .date
+                .map(|d| format!(r#" <time datetime="{d}">{d}</time>"#))
+                .unwrap_or_default();
+            format!(
+                r#"<li><a href="{}">{}</a>{date}</li>"#,
+                escape(&page.link),
+                escape(&page.title)
+            )
+        })
+        .collect::<String>();
+    let title = format!("Tagged “{}”", tag.name);
+    Ok(PageTemplate::builder()
+        .title(&title)
+        .path(std::path::Path::new("tags").join(slug))
+        .build(
+            state,
+            format!(
+                r#"<h1>{}</h1><ul class="links-list">{pages}</ul><p><a href="/tags">All tags</a></p>"#,
+                escape(&title)
+            ),
+        )?
+        .render()
+        .map_err(templates::Error::Template)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugs_are_words_and_dashes() {
+        assert_eq!(slug("Rust"), "rust");
+        assert_eq!(slug("Web Development"), "web-development");
+        assert_eq!(slug("camelCase"), "camelcase");
+        assert_eq!(slug(r#"a/b?c#d"e<f>"#), "a-b-c-d-e-f");
+        assert_eq!(slug("  C++ & Rust!  "), "c-rust");
+        assert_eq!(slug("Café"), "café");
+    }
+
+    #[test]
+    fn links_are_safe_in_attributes() {
+        assert_eq!(
+            link(r#"x" onmouseover="alert(1)"#).unwrap(),
+            "/tags/x-onmouseover-alert-1"
+        );
+        assert_eq!(link("Café").unwrap(), "/tags/caf%C3%A9");
+        assert_eq!(link("../../etc").unwrap(), "/tags/etc");
+        assert_eq!(link("?!"), None);
+    }
+
+    #[test]
+    fn tags_without_a_slug_are_skipped() {
+        let (_dir, state) =
+            utils::test_site(&[("post.md", "```toml\ntags = [\"?!\", \"Rust\"]\n```\n# Post")]);
+        let index = index(&state).unwrap();
+        assert_eq!(index.keys().collect::<Vec<_>>(), ["rust"]);
+    }
+}
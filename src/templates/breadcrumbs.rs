@@ -0,0 +1,105 @@
+use crate::{
+    markdown,
+    prelude::AppState,
+    utils::{self, order::url_path},
+};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct Crumb {
+    pub name: String,
+    pub link: String,
+}
+
+// One crumb for the root and for each directory above the page, then the page itself
+pub fn breadcrumbs(state: &AppState, path: &Path, title: &str) -> Vec<Crumb> {
+    if path.as_os_str().is_empty() {
+        return Vec::new();
+    }
+    let mut crumbs = vec![Crumb {
+        name: String::from("Home"),
+        link: String::from("/"),
+    }];
+    let mut dir = PathBuf::new();
+    let components = path.iter().collect::<Vec<_>>();
+    for component in components[..components.len() - 1].iter() {
+        dir.push(component);
+        let name = markdown::dir_title(state, &dir)
+            .or_else(|| utils::display_name(&dir))
+            .unwrap_or_else(|| component.to_string_lossy().into_owned());
+        crumbs.push(Crumb {
+            name,
+            link: format!("/{}", url_path(&dir).display()),
+        });
+    }
+    crumbs.push(Crumb {
+        name: title.to_string(),
+        link: format!("/{}", url_path(path).display()),
+    });
+    crumbs
+}
+
+// Files that can override the names of the page's ancestors
+pub fn breadcrumb_deps(root: &Path, path: &Path) -> Vec<PathBuf> {
+    path.ancestors()
+        .skip(1)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .flat_map(|dir| markdown::INDEX_FILES.map(|f| root.join(dir).join(f)))
+        .collect()
+}
+
+// schema.org BreadcrumbList, safe to embed in a <script> element
+pub fn json_ld(state: &AppState, crumbs: &[Crumb]) -> String {
+    if crumbs.is_empty() {
+        return String::new();
+    }
+    let base_url = state.base_url();
+    let items = crumbs
+        .iter()
+        .enumerate()
+        .map(|(i, crumb)| {
+            json!({
+                "@type": "ListItem",
+                "position": i + 1,
+                "name": crumb.name,
+                "item": format!("{base_url}{}", utils::percent_encode(&crumb.link)),
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "@context": "https://schema.org",
+        "@type": "BreadcrumbList",
+        "itemListElement": items,
+    })
+    .to_string()
+    .replace("</", r"<\/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_ld_items_are_encoded() {
+        let (_dir, state) = utils::test_site(&[]);
+        let crumbs = [
+            Crumb {
+                name: String::from("Home"),
+                link: String::from("/"),
+            },
+            Crumb {
+                name: String::from("Fish & Chips"),
+                link: String::from("/fish & chips/café"),
+            },
+        ];
+        let ld = serde_json::from_str::<serde_json::Value>(&json_ld(&state, &crumbs)).unwrap();
+        let base_url = state.base_url();
+        assert_eq!(ld["itemListElement"][0]["item"], format!("{base_url}/"));
+        assert_eq!(
+            ld["itemListElement"][1]["item"],
+            format!("{base_url}/fish%20%26%20chips/caf%C3%A9")
+        );
+        assert_eq!(ld["itemListElement"][1]["name"], "Fish & Chips");
+    }
+}
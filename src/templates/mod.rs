@@ -1,6 +1,16 @@
-use crate::utils::{self, nav};
+mod breadcrumbs;
+
+pub use breadcrumbs::{breadcrumb_deps, Crumb};
+
+use crate::{
+    config::Config,
+    prelude::AppState,
+    tags,
+    utils::{self, escape, nav, NavItem},
+};
 use askama::Template;
-use std::path::Path;
+use axum::http::StatusCode;
+use std::{path::PathBuf, sync::Arc};
 use thiserror::Error;
 use time::Date;
 use tracing::{debug, trace};
@@ -15,14 +25,26 @@ pub enum Error {
     Template(#[from] askama::Error),
 }
 
+impl Error {
+    pub fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
 #[derive(Template, Debug)]
 #[template(path = "page.html")]
 pub struct PageTemplate {
     title: String,
     last_modified: String,
     tags: String,
+    toc: String,
     content: String,
-    nav: String,
+    nav: Vec<NavItem>,
+    breadcrumbs: Vec<Crumb>,
+    breadcrumbs_ld: String,
+    unpublished: bool,
+    config: Arc<Config>,
+    live_reload: bool,
 }
 
 impl PageTemplate {
@@ -37,6 +59,9 @@ pub struct PageTemplateBuilder<T> {
     title: T,
     last_modified: Option<Date>,
     tags: Option<Vec<String>>,
+    toc: Option<String>,
+    path: Option<PathBuf>,
+    unpublished: bool,
 }
 
 impl PageTemplateBuilder<NoTitle> {
@@ -47,6 +72,9 @@ impl PageTemplateBuilder<NoTitle> {
             title: Title(title),
             last_modified: self.last_modified,
             tags: self.tags,
+            toc: self.toc,
+            path: self.path,
+            unpublished: self.unpublished,
         }
     }
 }
@@ -58,6 +86,9 @@ impl<T> PageTemplateBuilder<T> {
             title: self.title,
             last_modified: Some(last_modified.into()),
             tags: self.tags,
+            toc: self.toc,
+            path: self.path,
+            unpublished: self.unpublished,
         }
     }
 
@@ -67,6 +98,9 @@ impl<T> PageTemplateBuilder<T> {
             title: self.title,
             last_modified: self.last_modified,
             tags: Some(tags.into()),
+            toc: self.toc,
+            path: self.path,
+            unpublished: self.unpublished,
         }
     }
 
@@ -77,24 +111,89 @@ impl<T> PageTemplateBuilder<T> {
             self
         }
     }
+
+    pub fn toc(self, toc: impl Into<String>) -> PageTemplateBuilder<T> {
+        trace!("Adding table of contents");
+        PageTemplateBuilder {
+            title: self.title,
+            last_modified: self.last_modified,
+            tags: self.tags,
+            toc: Some(toc.into()),
+            path: self.path,
+            unpublished: self.unpublished,
+        }
+    }
+
+    // The page's path relative to the root, used to mark it in the nav
+    pub fn path(self, path: impl Into<PathBuf>) -> PageTemplateBuilder<T> {
+        PageTemplateBuilder {
+            title: self.title,
+            last_modified: self.last_modified,
+            tags: self.tags,
+            toc: self.toc,
+            path: Some(path.into()),
+            unpublished: self.unpublished,
+        }
+    }
+
+    // Drafts, scheduled and expired pages are marked when shown with `--drafts`
+    pub fn unpublished(self, unpublished: bool) -> PageTemplateBuilder<T> {
+        PageTemplateBuilder {
+            title: self.title,
+            last_modified: self.last_modified,
+            tags: self.tags,
+            toc: self.toc,
+            path: self.path,
+            unpublished,
+        }
+    }
+
+    pub fn toc_opt(self, toc: Option<String>) -> PageTemplateBuilder<T> {
+        if let Some(t) = toc {
+            self.toc(t)
+        } else {
+            self
+        }
+    }
 }
 
 impl PageTemplateBuilder<Title> {
-    pub fn build(self, root: impl AsRef<Path>, content: impl Into<String>) -> R<PageTemplate> {
+    pub fn build(self, state: &AppState, content: impl Into<String>) -> R<PageTemplate> {
         let last_modified = match self.last_modified {
             Some(d) => format!(r#"<p class="last_modified">Last updated: {d}</p>"#),
             None => String::new(),
         };
-        let tags = match self.tags {
-            Some(t) => format!(r#"<p class="tags">{}</p>"#, t.join(" · ")),
-            None => String::new(),
+        let tags = self
+            .tags
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|tag| {
+                let link = tags::link(tag)?;
+                Some(format!(r#"<a href="{link}">{}</a>"#, escape(tag)))
+            })
+            .collect::<Vec<_>>();
+        let tags = if tags.is_empty() {
+            String::new()
+        } else {
+            format!(r#"<p class="tags">{}</p>"#, tags.join(" · "))
         };
+        let breadcrumbs = self
+            .path
+            .as_deref()
+            .map(|path| breadcrumbs::breadcrumbs(state, path, &self.title.0))
+            .unwrap_or_default();
         let pt = PageTemplate {
+            breadcrumbs_ld: breadcrumbs::json_ld(state, &breadcrumbs),
+            breadcrumbs,
+            unpublished: self.unpublished,
             title: self.title.0,
             content: content.into(),
             last_modified,
             tags,
-            nav: nav(root)?,
+            toc: self.toc.unwrap_or_default(),
+            nav: nav(state, self.path.as_deref())?,
+            config: state.config.clone(),
+            live_reload: state.reload.is_some(),
         };
         Ok(pt)
     }
@@ -1,15 +1,26 @@
 pub mod iterator;
+pub mod order;
 pub mod path;
 
+use axum::http::StatusCode;
 use convert_case::{Case, Casing};
 use std::{
+    fmt::{self, Display},
     fs::{read_dir, DirEntry},
     path::{Path, PathBuf},
 };
 use thiserror::Error;
+use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
 use tracing::trace;
 
-use crate::utils::path::PathExt;
+use crate::{
+    markdown::{self, INDEX_FILES},
+    prelude::AppState,
+    utils::path::PathExt,
+};
+
+// Version control and tool directories, which are never served or exported
+pub const TOOLING_DIRS: [&str; 4] = [".git", ".hg", ".svn", ".syntaxes"];
 
 pub type R<T> = core::result::Result<T, Error>;
 #[derive(Debug, Error)]
@@ -30,25 +41,245 @@ pub enum Error {
     IO(#[from] std::io::Error),
 }
 
-pub fn nav(root: impl AsRef<Path>) -> R<String> {
+impl Error {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Error::IO(err) => crate::error::io_status(err),
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NavItem {
+    pub name: String,
+    pub link: String,
+    pub current: Option<Current>,
+    pub children: Vec<NavItem>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Current {
+    Page,
+    Ancestor,
+}
+
+impl Display for NavItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let current = match self.current {
+            Some(Current::Page) => r#" aria-current="page""#,
+            Some(Current::Ancestor) => r#" aria-current="true""#,
+            None => "",
+        };
+        write!(
+            f,
+            r#"<li><a href="{}"{current}>{}</a>"#,
+            escape(&self.link),
+            escape(&self.name)
+        )?;
+        if !self.children.is_empty() {
+            f.write_str("<ul>")?;
+            for child in self.children.iter() {
+                write!(f, "{child}")?;
+            }
+            f.write_str("</ul>")?;
+        }
+        f.write_str("</li>")
+    }
+}
+
+// Top-level directories, then every shown page and directory below them down to the configured
+// depth. `current` is the page being rendered, which is marked along with its ancestors
+pub fn nav(state: &AppState, current: Option<&Path>) -> R<Vec<NavItem>> {
     trace!("Building nav");
-    let nav_home_link = String::from(r#"<li><a href="/">Home</a></li>"#);
-    let mut entries = read_dir(root)?
+    let home = NavItem {
+        name: String::from("Home"),
+        link: String::from("/"),
+        current: current
+            .filter(|c| c.as_os_str().is_empty())
+            .map(|_| Current::Page),
+        children: Vec::new(),
+    };
+    let mut items = vec![home];
+    items.extend(nav_level(state, Path::new(""), 1, current)?);
+    Ok(items)
+}
+
+fn nav_level(
+    state: &AppState,
+    dir: &Path,
+    level: usize,
+    current: Option<&Path>,
+) -> R<Vec<NavItem>> {
+    if level > state.config.site.nav_depth {
+        return Ok(Vec::new());
+    }
+    let entries = read_dir(state.root.join(dir))?
         .filter_map(core::result::Result::ok)
-        .filter(|e| is_shown_dir_only(e).unwrap_or(false))
+        .filter(|e| {
+            if level == 1 {
+                is_shown_dir_only(e).unwrap_or(false)
+            } else {
+                is_shown(e).unwrap_or(false)
+                    && !is_shadowed(e)
+                    && !is_index(e)
+                    && markdown::is_page_visible(state, &e.path())
+            }
+        })
         .map(to_display_and_fname)
         .filter_map(core::result::Result::ok)
+        .map(|(name, fname)| (name, dir.join(fname)))
         .collect::<Vec<_>>();
-    entries.sort_by(|a, b| natord::compare(&a.0, &b.0));
-    Ok(entries
+    let sort = markdown::dir_metadata(state, dir)
+        .map(|md| md.sort)
+        .unwrap_or_default();
+    let entries = order::sort(state, dir, sort, entries, |(_, path)| path);
+
+    entries
+        .into_iter()
+        .map(|(name, path)| {
+            let path = path.with_extension("");
+            let marker = current.and_then(|c| {
+                if c == path {
+                    Some(Current::Page)
+                } else if c.starts_with(&path) {
+                    Some(Current::Ancestor)
+                } else {
+                    None
+                }
+            });
+            let children = if state.root.join(&path).is_dir() {
+                nav_level(state, &path, level + 1, current)?
+            } else {
+                Vec::new()
+            };
+            Ok(NavItem {
+                name,
+                link: format!("/{}", order::url_path(&path).display()),
+                current: marker,
+                children,
+            })
+        })
+        .collect()
+}
+
+pub fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    pulldown_cmark_escape::escape_html(&mut escaped, s).expect("writing to a String cannot fail");
+    escaped
+}
+
+// Percent-encodes everything in a URL path except unreserved characters and `/`
+pub fn percent_encode(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for b in path.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(char::from(b))
+            }
+            _ => encoded.push_str(&format!("%{b:02X}")),
+        }
+    }
+    encoded
+}
+
+pub fn to_offset_date_time(dt: &toml::value::Datetime) -> Option<OffsetDateTime> {
+    let date = dt.date?;
+    let date = Date::from_calendar_date(
+        date.year.into(),
+        Month::try_from(date.month).ok()?,
+        date.day,
+    )
+    .ok()?;
+    let time = match dt.time {
+        Some(t) => Time::from_hms(t.hour, t.minute, t.second.unwrap_or_default()).ok()?,
+        None => Time::MIDNIGHT,
+    };
+    let offset = match dt.offset {
+        Some(toml::value::Offset::Custom { minutes }) => {
+            UtcOffset::from_whole_seconds(i32::from(minutes) * 60).ok()?
+        }
+        Some(toml::value::Offset::Z) | None => UtcOffset::UTC,
+    };
+    Some(PrimitiveDateTime::new(date, time).assume_offset(offset))
+}
+
+// Every entry below `root`, relative to it, leaving out hidden ones
+pub fn walk(root: impl AsRef<Path>) -> R<Vec<PathBuf>> {
+    trace!("Walking content root");
+    walk_filtered(root.as_ref(), false)
+}
+
+// Hidden assets such as `.fonts/` are served, but never listed
+pub fn hidden_assets(root: impl AsRef<Path>) -> R<Vec<PathBuf>> {
+    let root = root.as_ref();
+    trace!("Walking content root for hidden assets");
+    Ok(walk_filtered(root, true)?
         .into_iter()
-        .map(|(display_name, path)| {
-            format!(
-                r#"<li><a href="/{}">{display_name}</a></li>"#,
-                path.display()
-            )
+        .filter(|rel| {
+            rel.has_hidden_component() && is_hidden_asset(rel) && root.join(rel).is_file()
         })
-        .fold(nav_home_link, |acc, e| acc + &e))
+        .collect())
+}
+
+// Hidden pages, captions and `.order` files are private, as is anything under a tooling directory
+pub fn is_hidden_asset(rel_path: &Path) -> bool {
+    rel_path.extension().is_some_and(|ext| ext != "md")
+        && !rel_path
+            .components()
+            .any(|c| TOOLING_DIRS.iter().any(|dir| c.as_os_str() == *dir))
+}
+
+fn walk_filtered(root: &Path, hidden: bool) -> R<Vec<PathBuf>> {
+    fn walk_dir(root: &Path, dir: &Path, hidden: bool, acc: &mut Vec<PathBuf>) -> R<()> {
+        for entry in read_dir(root.join(dir))? {
+            let name = entry?.file_name();
+            let rel = dir.join(&name);
+            if TOOLING_DIRS.iter().any(|dir| name == *dir)
+                || (!hidden && rel.is_hidden().unwrap_or(true))
+            {
+                continue;
+            }
+            let is_dir = root.join(&rel).is_dir();
+            acc.push(rel.clone());
+            if is_dir {
+                walk_dir(root, &rel, hidden, acc)?;
+            }
+        }
+        Ok(())
+    }
+
+    let mut entries = Vec::new();
+    walk_dir(root, Path::new(""), hidden, &mut entries)?;
+    entries.sort();
+    Ok(entries)
+}
+
+// Everything `nav` reads, so pages can be re-rendered when it changes
+pub fn nav_deps(root: impl AsRef<Path>, depth: usize) -> Vec<PathBuf> {
+    fn dirs(root: &Path, dir: &Path, level: usize, depth: usize, acc: &mut Vec<PathBuf>) {
+        acc.push(root.join(dir));
+        acc.extend(INDEX_FILES.map(|f| root.join(dir).join(f)));
+        acc.push(root.join(dir).join(order::ORDER_FILE));
+        if level > depth {
+            return;
+        }
+        let Ok(entries) = read_dir(root.join(dir)) else {
+            return;
+        };
+        for entry in entries.filter_map(core::result::Result::ok) {
+            if is_shown_dir_only(&entry).unwrap_or(false) {
+                dirs(root, &dir.join(entry.file_name()), level + 1, depth, acc);
+            } else if level > 1 && is_shown(&entry).unwrap_or(false) {
+                // Pages may set their own weight
+                acc.push(entry.path());
+            }
+        }
+    }
+
+    let mut deps = Vec::new();
+    dirs(root.as_ref(), Path::new(""), 1, depth, &mut deps);
+    deps
 }
 
 pub fn is_shown(entry: &DirEntry) -> R<bool> {
@@ -76,16 +307,40 @@ pub fn is_shown_dir_only(entry: &DirEntry) -> R<bool> {
     Ok(!hidden && is_dir)
 }
 
+// A page with a directory of the same name can't be reached
+fn is_shadowed(entry: &DirEntry) -> bool {
+    let path = entry.path();
+    path.extension().is_some_and(|ext| ext == "md") && path.with_extension("").is_dir()
+}
+
+pub fn is_index(entry: &DirEntry) -> bool {
+    INDEX_FILES.iter().any(|f| entry.file_name() == *f)
+}
+
 fn to_display_and_fname(entry: DirEntry) -> R<(String, PathBuf)> {
     let path: PathBuf = entry
         .path()
         .file_name()
         .ok_or(Error::FileName(entry.path()))?
         .into();
-    let display_name = path
-        .file_root()
-        .map(String::from)
-        .map(|s| s.to_case(Case::Title))
-        .ok_or(Error::FileRoot(entry.path()))?;
+    let display_name = display_name(&path).ok_or(Error::FileRoot(entry.path()))?;
     Ok((display_name, path))
 }
+
+pub fn display_name(path: &Path) -> Option<String> {
+    path.file_root()
+        .map(|s| order::strip_order_prefix(s).to_case(Case::Title))
+}
+
+// A content root holding `files`, which is deleted when the `TempDir` is dropped
+#[cfg(test)]
+pub(crate) fn test_site(files: &[(&str, &str)]) -> (tempfile::TempDir, AppState) {
+    let dir = tempfile::tempdir().unwrap();
+    for (path, contents) in files {
+        let path = dir.path().join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+    let state = AppState::builder().root(dir.path()).port(0).build();
+    (dir, state)
+}
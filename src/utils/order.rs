@@ -0,0 +1,328 @@
+use crate::{
+    markdown::{self, INDEX_FILES},
+    prelude::AppState,
+    utils::path::PathExt,
+    Metadata, SortOrder,
+};
+use std::{
+    cmp::{Ordering, Reverse},
+    ffi::OsStr,
+    fs::{self, read_dir},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use time::OffsetDateTime;
+use tracing::trace;
+
+// Lists a directory's entries in the order they should appear, one per line
+pub const ORDER_FILE: &str = ".order";
+
+// `01-intro` -> `intro`. Only one prefix is removed, and never the whole name
+pub fn strip_order_prefix(name: &str) -> &str {
+    let digits = name.len() - name.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    match name[digits..].strip_prefix(['-', '_', '.', ' ']) {
+        Some(rest) if digits > 0 && !rest.is_empty() => rest,
+        _ => name,
+    }
+}
+
+// `10-intro` -> 10, used so prefixed entries sort by number rather than by name
+fn order_prefix(name: &str) -> Option<u64> {
+    let rest = strip_order_prefix(name);
+    (rest.len() < name.len())
+        .then(|| name[..name.len() - rest.len() - 1].parse().ok())
+        .flatten()
+}
+
+// The URL of a page or directory, relative to the root
+pub fn url_path(rel_path: &Path) -> PathBuf {
+    let rel_path = match rel_path.extension() {
+        Some(ext) if ext == "md" => rel_path.with_extension(""),
+        _ => rel_path.to_path_buf(),
+    };
+    rel_path
+        .iter()
+        .map(|c| c.to_str().map_or(c, |s| OsStr::new(strip_order_prefix(s))))
+        .collect()
+}
+
+// Undoes `url_path`, finding the prefixed entry each component refers to. Pages are returned
+// without their extension, as they are requested
+pub fn resolve(root: &Path, req_path: &Path) -> PathBuf {
+    let mut resolved = PathBuf::new();
+    for component in req_path.iter() {
+        let candidate = resolved.join(component);
+        if root.join(&candidate).exists() || root.join(&candidate).with_extension("md").is_file() {
+            resolved = candidate;
+            continue;
+        }
+        let found = read_dir(root.join(&resolved)).ok().and_then(|entries| {
+            entries.filter_map(Result::ok).find_map(|e| {
+                let path = PathBuf::from(e.file_name());
+                let name = path.to_str()?;
+                if strip_order_prefix(name) == component {
+                    return Some(path);
+                }
+                let page = path.with_extension("");
+                let is_md = path.extension().is_some_and(|ext| ext == "md");
+                (is_md && strip_order_prefix(page.to_str()?) == component).then_some(page)
+            })
+        });
+        match found {
+            Some(name) => {
+                trace!(
+                    r#"Resolved "{}" to "{}""#,
+                    component.display(),
+                    name.display()
+                );
+                resolved.push(name);
+            }
+            None => resolved = candidate,
+        }
+    }
+    resolved
+}
+
+// Where an entry's weight and date come from
+fn metadata(state: &AppState, rel_path: &Path) -> Option<Metadata> {
+    if state.root.join(rel_path).is_dir() {
+        markdown::dir_metadata(state, rel_path)
+    } else {
+        markdown::read_metadata(state, rel_path).ok()
+    }
+}
+
+fn order_file(state: &AppState, dir: &Path) -> Vec<String> {
+    fs::read_to_string(state.root.join(dir).join(ORDER_FILE))
+        .map(|order| {
+            order
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+struct Key {
+    position: Option<usize>,
+    weight: Option<i64>,
+    prefix: Option<u64>,
+    name: String,
+    date: Option<OffsetDateTime>,
+    modified: Option<SystemTime>,
+}
+
+// Entries named in the `.order` file come first, then those with a `weight`, then the rest in
+// the directory's sort order
+pub fn sort<T>(
+    state: &AppState,
+    dir: &Path,
+    sort: SortOrder,
+    entries: Vec<T>,
+    path: impl Fn(&T) -> &Path,
+) -> Vec<T> {
+    let order = order_file(state, dir);
+    let mut keyed = entries
+        .into_iter()
+        .map(|entry| {
+            let rel_path = path(&entry);
+            let file_name = rel_path.file_name().and_then(|f| f.to_str()).unwrap_or("");
+            let file_root = rel_path.file_root().unwrap_or("");
+            let position = order.iter().position(|o| {
+                o == file_name || o == file_root || o == strip_order_prefix(file_root)
+            });
+            let metadata = metadata(state, rel_path);
+            let key = Key {
+                position,
+                weight: metadata.as_ref().and_then(|md| md.weight),
+                prefix: order_prefix(file_name),
+                name: file_name.to_string(),
+                date: metadata.as_ref().and_then(Metadata::date),
+                modified: state
+                    .root
+                    .join(rel_path)
+                    .metadata()
+                    .and_then(|md| md.modified())
+                    .ok(),
+            };
+            (key, entry)
+        })
+        .collect::<Vec<_>>();
+
+    keyed.sort_by(|(a, _), (b, _)| {
+        last_if_none(a.position, b.position)
+            .then_with(|| last_if_none(a.weight, b.weight))
+            .then_with(|| match sort {
+                SortOrder::Name => by_name(a, b),
+                SortOrder::NameDesc => by_name(b, a),
+                SortOrder::Date => last_if_none(a.date, b.date),
+                SortOrder::DateDesc => last_if_none(a.date.map(Reverse), b.date.map(Reverse)),
+                SortOrder::Modified => last_if_none(a.modified, b.modified),
+                SortOrder::ModifiedDesc => {
+                    last_if_none(a.modified.map(Reverse), b.modified.map(Reverse))
+                }
+            })
+            .then_with(|| by_name(a, b))
+    });
+    keyed.into_iter().map(|(_, entry)| entry).collect()
+}
+
+// Numbered entries by their number, then everything by file name
+fn by_name(a: &Key, b: &Key) -> Ordering {
+    last_if_none(a.prefix, b.prefix).then_with(|| natord::compare(&a.name, &b.name))
+}
+
+fn last_if_none<T: Ord>(a: Option<T>, b: Option<T>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+// Everything `sort` reads for a directory's entries
+pub fn deps(root: &Path, dir: &Path, entries: impl IntoIterator<Item = PathBuf>) -> Vec<PathBuf> {
+    let mut deps = vec![root.join(dir).join(ORDER_FILE)];
+    for entry in entries {
+        let fs_path = root.join(&entry);
+        if fs_path.is_dir() {
+            deps.extend(INDEX_FILES.map(|f| fs_path.join(f)));
+        } else {
+            deps.push(fs_path);
+        }
+    }
+    deps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_site;
+    use std::fs::create_dir;
+
+    fn sorted(state: &AppState, dir: &str, sort_order: SortOrder) -> Vec<String> {
+        let mut entries = read_dir(state.root.join(dir))
+            .unwrap()
+            .map(|e| Path::new(dir).join(e.unwrap().file_name()))
+            .filter(|p| p.file_name().is_some_and(|f| f != ORDER_FILE))
+            .collect::<Vec<_>>();
+        entries.sort();
+        sort(state, Path::new(dir), sort_order, entries, |p| p)
+            .into_iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn strips_order_prefixes() {
+        assert_eq!(strip_order_prefix("01-intro"), "intro");
+        assert_eq!(strip_order_prefix("2_setup.md"), "setup.md");
+        assert_eq!(strip_order_prefix("10.advanced"), "advanced");
+        assert_eq!(strip_order_prefix("3 notes"), "notes");
+        assert_eq!(strip_order_prefix("01-02-twice"), "02-twice");
+        assert_eq!(strip_order_prefix("2024"), "2024");
+        assert_eq!(strip_order_prefix("01-"), "01-");
+        assert_eq!(strip_order_prefix("intro"), "intro");
+        assert_eq!(strip_order_prefix("v2-intro"), "v2-intro");
+    }
+
+    #[test]
+    fn url_paths_strip_every_component() {
+        assert_eq!(
+            url_path(Path::new("01-guide/02-setup.md")),
+            PathBuf::from("guide/setup")
+        );
+        assert_eq!(url_path(Path::new("style.css")), PathBuf::from("style.css"));
+    }
+
+    #[test]
+    fn resolves_prefixed_paths() {
+        let (_dir, state) = test_site(&[
+            ("01-guide/02-setup.md", ""),
+            ("01-guide/03-images/cat.png", ""),
+            ("about.md", ""),
+        ]);
+        let resolve = |p: &str| resolve(&state.root, Path::new(p));
+        assert_eq!(resolve("guide"), PathBuf::from("01-guide"));
+        assert_eq!(resolve("guide/setup"), PathBuf::from("01-guide/02-setup"));
+        assert_eq!(
+            resolve("guide/images/cat.png"),
+            PathBuf::from("01-guide/03-images/cat.png")
+        );
+        assert_eq!(
+            resolve("01-guide/setup"),
+            PathBuf::from("01-guide/02-setup")
+        );
+        assert_eq!(resolve("about"), PathBuf::from("about"));
+        assert_eq!(resolve("missing/page"), PathBuf::from("missing/page"));
+    }
+
+    #[test]
+    fn sorts_by_prefix_number_not_name() {
+        let (_dir, state) = test_site(&[
+            ("d/10-alpha.md", ""),
+            ("d/02-zeta.md", ""),
+            ("d/1-middle.md", ""),
+            ("d/beta.md", ""),
+            ("d/aardvark.md", ""),
+        ]);
+        assert_eq!(
+            sorted(&state, "d", SortOrder::Name),
+            [
+                "1-middle.md",
+                "02-zeta.md",
+                "10-alpha.md",
+                "aardvark.md",
+                "beta.md"
+            ]
+        );
+        assert_eq!(
+            sorted(&state, "d", SortOrder::NameDesc),
+            [
+                "beta.md",
+                "aardvark.md",
+                "10-alpha.md",
+                "02-zeta.md",
+                "1-middle.md"
+            ]
+        );
+    }
+
+    #[test]
+    fn order_file_then_weight_then_name() {
+        let (_dir, state) = test_site(&[
+            ("d/01-a.md", ""),
+            ("d/02-b.md", "```toml\nweight = 2\n```\n"),
+            ("d/03-c.md", "```toml\nweight = 1\n```\n"),
+            ("d/04-d.md", "```toml\nweight = 1\n```\n"),
+            ("d/sub/index.md", "```toml\nweight = 3\n```\n"),
+            ("d/z.md", ""),
+            ("d/.order", "# comment\nz\n04-d.md\n"),
+        ]);
+        assert_eq!(
+            sorted(&state, "d", SortOrder::Name),
+            ["z.md", "04-d.md", "03-c.md", "02-b.md", "sub", "01-a.md"]
+        );
+    }
+
+    #[test]
+    fn sorts_by_date() {
+        let (_dir, state) = test_site(&[
+            ("d/old.md", "```toml\ndate = 2020-01-01\n```\n"),
+            ("d/new.md", "```toml\ndate = 2024-01-01\n```\n"),
+            ("d/undated.md", ""),
+        ]);
+        create_dir(state.root.join("d/empty")).unwrap();
+        assert_eq!(
+            sorted(&state, "d", SortOrder::Date),
+            ["old.md", "new.md", "empty", "undated.md"]
+        );
+        assert_eq!(
+            sorted(&state, "d", SortOrder::DateDesc),
+            ["new.md", "old.md", "empty", "undated.md"]
+        );
+    }
+}
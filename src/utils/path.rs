@@ -1,8 +1,9 @@
-use std::path::Path;
+use std::path::{Component, Path};
 
 pub trait PathExt {
     fn file_root(&self) -> Option<&str>;
     fn is_hidden(&self) -> Option<bool>;
+    fn has_hidden_component(&self) -> bool;
 }
 
 impl PathExt for Path {
@@ -19,4 +20,11 @@ impl PathExt for Path {
             .map(std::ffi::OsStr::to_string_lossy)
             .map(|s| s.starts_with('.'))
     }
+
+    fn has_hidden_component(&self) -> bool {
+        self.components().any(|c| match c {
+            Component::Normal(name) => name.to_string_lossy().starts_with('.'),
+            _ => false,
+        })
+    }
 }